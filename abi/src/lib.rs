@@ -1,4 +1,4 @@
-use async_graphql::{Enum, SimpleObject};
+use async_graphql::{ComplexObject, Enum, InputObject, SimpleObject};
 use linera_sdk::{
     linera_base_types::{AccountOwner, Amount, ChainId, Timestamp},
     graphql::GraphQLMutationRoot,
@@ -26,6 +26,21 @@ pub struct Market {
     pub down_pool: Amount,
     pub oracle_mode: OracleMode,
     pub resolution_source: Option<String>,
+    pub limits: MarketLimits,
+    /// Platform fee in effect when the market was created
+    pub fee_percent: u8,
+}
+
+/// Exposure caps for a market; `None` means uncapped
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject, InputObject, PartialEq, Eq)]
+#[graphql(input_name = "MarketLimitsInput")]
+pub struct MarketLimits {
+    /// Largest single bet
+    pub max_bet: Option<Amount>,
+    /// Largest total stake one user may hold in the market
+    pub max_user_stake: Option<Amount>,
+    /// Largest total pool
+    pub max_pool: Option<Amount>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
@@ -96,6 +111,104 @@ pub struct Payout {
     pub amount: Amount,
 }
 
+/// A closed (or partially closed) position, kept in the owner's position history
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct PositionRecord {
+    pub market_id: u64,
+    pub owner: AccountOwner,
+    pub side: Outcome,
+    /// Stake taken off the market
+    pub stake: Amount,
+    /// Tokens returned to the owner
+    pub proceeds: Amount,
+    pub realized_pnl: SignedAmount,
+    pub closed_at: Timestamp,
+}
+
+/// Profit or loss: an amount together with its sign. Zero is never negative.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SimpleObject)]
+pub struct SignedAmount {
+    pub magnitude: Amount,
+    pub negative: bool,
+}
+
+// =============================================================================
+// ORDER BOOK TYPES
+// =============================================================================
+
+/// A limit order to buy or sell stake in one outcome of a market
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct LimitOrder {
+    pub id: u64,
+    pub market_id: u64,
+    pub owner: AccountOwner,
+    pub outcome: Outcome,
+    pub side: OrderSide,
+    /// Price per unit of stake, in basis points (10,000 = par)
+    pub price_bps: u32,
+    pub quantity: Amount,
+    pub filled: Amount,
+    /// Tokens held in escrow for a buy order
+    pub escrow: Amount,
+    pub status: OrderStatus,
+    pub placed_at: Timestamp,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
+pub enum OrderStatus {
+    Open,
+    Filled,
+    Cancelled,
+}
+
+/// Aggregated quantity resting at one price
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct DepthLevel {
+    pub price_bps: u32,
+    pub quantity: Amount,
+    pub orders: u32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
+pub struct OrderBookDepth {
+    /// Best (highest) bid first
+    pub bids: Vec<DepthLevel>,
+    /// Best (lowest) ask first
+    pub asks: Vec<DepthLevel>,
+}
+
+// =============================================================================
+// LIQUIDITY TYPES
+// =============================================================================
+
+/// Liquidity provided to a market by LPs
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
+pub struct LiquidityPool {
+    pub market_id: u64,
+    pub total_shares: Amount,
+    /// LP capital seeded into the Up pool
+    pub up_seed: Amount,
+    /// LP capital seeded into the Down pool
+    pub down_seed: Amount,
+    /// Fees accrued to LPs
+    pub fees: Amount,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct LpPosition {
+    pub market_id: u64,
+    pub owner: AccountOwner,
+    pub shares: Amount,
+    /// Current redemption value of the shares
+    pub value: Amount,
+}
+
 // =============================================================================
 // AI ORACLE TYPES
 // =============================================================================
@@ -131,16 +244,79 @@ pub struct DataSource {
 // =============================================================================
 
 #[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+#[graphql(complex)]
 pub struct AIAgent {
     pub id: u64,
+    /// Account that created the agent
+    pub owner: AccountOwner,
+    /// Keys the owner has delegated to run the agent
+    pub operators: Vec<AccountOwner>,
     pub name: String,
     pub strategy: AgentStrategy,
+    /// Positions settled as a win or a loss
     pub total_bets: u64,
     pub wins: u64,
     pub losses: u64,
-    pub total_profit: Amount,
-    pub accuracy: f32,
+    /// Realized profit and loss across settled positions, net of performance fees
+    pub total_profit: SignedAmount,
+    /// Stake put into settled positions
+    pub total_staked: Amount,
+    /// Part of `total_staked` that was on the winning outcome
+    pub winning_stake: Amount,
+    /// Highest `total_profit` reached so far
+    pub peak_profit: SignedAmount,
+    /// Largest fall of `total_profit` from a previous peak
+    pub max_drawdown: Amount,
+    /// Sum of per-position returns (PnL / stake), in basis points
+    #[graphql(skip)]
+    pub return_sum_bps: i128,
+    /// Sum of squared per-position returns, in squared basis points
+    #[graphql(skip)]
+    pub return_sq_sum_bps: u128,
     pub active: bool,
+    /// Tokens the agent holds in the agent app's account
+    pub balance: Amount,
+    /// Markets the agent has a stake in that are not settled yet
+    pub open_positions: u64,
+    /// Stake in positions that are not settled yet
+    pub open_stake: Amount,
+    /// Rules every bet the agent places must satisfy
+    pub config: StrategyConfig,
+    /// Day (in `AGENT_SPEND_WINDOW_MICROS` since the epoch) of `spent_today`
+    pub spend_day: u64,
+    /// Stake placed during `spend_day`
+    pub spent_today: Amount,
+    /// Vault shares outstanding, including queued ones
+    pub total_shares: Amount,
+    /// Shares waiting to be redeemed once open positions settle
+    pub queued_shares: Amount,
+    /// Owner's cut of profit above the previous peak, in basis points
+    pub performance_fee_bps: u32,
+}
+
+/// Per-agent betting rules, set by the owner; `None` means no limit
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject, InputObject, PartialEq, Eq)]
+#[graphql(input_name = "StrategyConfigInput")]
+pub struct StrategyConfig {
+    /// Categories the agent may bet on; empty means any
+    pub categories: Vec<MarketCategory>,
+    /// Lowest pool share of the backed side, in basis points
+    pub min_odds_bps: Option<u32>,
+    /// Highest pool share of the backed side, in basis points
+    pub max_odds_bps: Option<u32>,
+    /// Share of the Kelly-optimal stake a bet may take, in basis points
+    /// (5,000 = half Kelly). Bets without a win probability are refused.
+    pub kelly_fraction_bps: Option<u32>,
+    /// Stop betting once realized losses reach this amount
+    pub stop_loss: Option<Amount>,
+    /// Most the agent may stake per day
+    pub max_daily_spend: Option<Amount>,
+    /// Most unsettled positions held at once
+    pub max_open_positions: Option<u64>,
+    /// Largest single bet
+    pub max_bet: Option<Amount>,
+    /// Largest total stake in unsettled positions
+    pub max_exposure: Option<Amount>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
@@ -165,18 +341,45 @@ pub const MARKET_CREATION_FEE: u128 = 1_000_000;
 /// Platform fee: 5%
 pub const PLATFORM_FEE_PERCENT: u8 = 5;
 
+/// Share of trading and resolution fees paid to LPs: 50%
+pub const LP_FEE_SHARE_PERCENT: u8 = 50;
+
 /// Min market duration: 1 minute
 pub const MIN_MARKET_DURATION_MICROS: u64 = 60_000_000;
 
 /// Max market duration: 7 days
 pub const MAX_MARKET_DURATION_MICROS: u64 = 604_800_000_000;
 
+/// Order prices are expressed in basis points of stake
+pub const PRICE_BPS_SCALE: u32 = 10_000;
+
+/// Max order price: 100x stake
+pub const MAX_ORDER_PRICE_BPS: u32 = 1_000_000;
+
+/// Most bets a single strategy run may place
+pub const AGENT_MAX_BETS_PER_RUN: u32 = 5;
+
+/// Max agent performance fee: 30%
+pub const MAX_PERFORMANCE_FEE_BPS: u32 = 3_000;
+
+/// Window over which an agent's daily spend is counted: 24 hours
+pub const AGENT_SPEND_WINDOW_MICROS: u64 = 86_400_000_000;
+
 /// Daily bonus amount: 100 tokens
-pub const DAILY_BONUS_AMOUNT: u128 = 100_000_000;
+pub const DAILY_BONUS_AMOUNT: u128 = 100_000_000_000_000_000_000;
+
+/// Daily bonus streak window: a claim within 24 hours of becoming available keeps the streak
+pub const DAILY_BONUS_STREAK_WINDOW_MICROS: u64 = 86_400_000_000;
 
 /// Daily bonus cooldown: 24 hours
 pub const DAILY_BONUS_COOLDOWN_MICROS: u64 = 86_400_000_000;
 
+/// Default unbonding period for unstaked tokens: 7 days
+pub const STAKE_UNBONDING_PERIOD_MICROS: u64 = 604_800_000_000;
+
+/// Fixed-point scale of the staking reward index
+pub const REWARD_INDEX_SCALE: u128 = 1_000_000_000_000_000_000;
+
 // =============================================================================
 // HELPER FUNCTIONS
 // =============================================================================
@@ -206,9 +409,611 @@ impl Market {
     pub fn odds_down(&self) -> f64 {
         1.0 - self.odds_up()
     }
+
+    /// Payout `amount` on `prediction` would earn if it won, with the bet included
+    pub fn implied_payout_after(&self, prediction: Outcome, amount: Amount) -> Amount {
+        let mut market = self.clone();
+        market.total_pool = market.total_pool.saturating_add(amount);
+        let side_pool = match prediction {
+            Outcome::Up => self.up_pool,
+            Outcome::Down => self.down_pool,
+        }
+        .saturating_add(amount);
+        market.payout_for(amount, side_pool)
+    }
+
+    /// Share of the total pool on `prediction` after a bet of `amount`, in basis points
+    pub fn pool_share_after_bps(&self, prediction: Outcome, amount: Amount) -> u32 {
+        let total = self.total_pool.saturating_add(amount);
+        let side_pool = match prediction {
+            Outcome::Up => self.up_pool,
+            Outcome::Down => self.down_pool,
+        }
+        .saturating_add(amount);
+        mul_div(
+            u128::from(side_pool),
+            u128::from(PRICE_BPS_SCALE),
+            u128::from(total),
+        ) as u32
+    }
+
+    /// Winnings for `stake` on the winning side, net of the platform fee
+    pub fn payout_for(&self, stake: Amount, winning_pool: Amount) -> Amount {
+        let net_pool = mul_div(
+            u128::from(self.total_pool),
+            u128::from(100 - self.fee_percent.min(100)),
+            100,
+        );
+        Amount::from_attos(mul_div(
+            u128::from(stake),
+            net_pool,
+            u128::from(winning_pool),
+        ))
+    }
+}
+
+impl MarketLimits {
+    /// These limits, tightened field by field to `caps`. An uncapped field takes
+    /// the cap, so a market can only be made stricter than the protocol allows.
+    pub fn within(&self, caps: &MarketLimits) -> MarketLimits {
+        fn tighter(limit: Option<Amount>, cap: Option<Amount>) -> Option<Amount> {
+            match (limit, cap) {
+                (Some(limit), Some(cap)) => Some(limit.min(cap)),
+                (limit, cap) => limit.or(cap),
+            }
+        }
+
+        MarketLimits {
+            max_bet: tighter(self.max_bet, caps.max_bet),
+            max_user_stake: tighter(self.max_user_stake, caps.max_user_stake),
+            max_pool: tighter(self.max_pool, caps.max_pool),
+        }
+    }
+
+    /// Check a new stake of `amount` against the caps. `user_stake` is what the
+    /// user already holds in the market; pass `None` to skip per-user checks.
+    pub fn check(
+        &self,
+        market: &Market,
+        amount: Amount,
+        user_stake: Option<Amount>,
+    ) -> Result<(), String> {
+        if let Some(max_bet) = self.max_bet {
+            if amount > max_bet {
+                return Err(format!("Bet of {} exceeds the maximum bet of {}", amount, max_bet));
+            }
+        }
+        if let (Some(max_user_stake), Some(held)) = (self.max_user_stake, user_stake) {
+            let total = held.saturating_add(amount);
+            if total > max_user_stake {
+                return Err(format!(
+                    "Total stake of {} exceeds the per-user limit of {}",
+                    total, max_user_stake
+                ));
+            }
+        }
+        if let Some(max_pool) = self.max_pool {
+            let pool = market.total_pool.saturating_add(amount);
+            if pool > max_pool {
+                return Err(format!(
+                    "Pool of {} would exceed the market limit of {}",
+                    pool, max_pool
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LiquidityPool {
+    pub fn new(market_id: u64) -> Self {
+        LiquidityPool {
+            market_id,
+            ..Default::default()
+        }
+    }
+
+    /// Value of all LP shares: seeded capital (or its winnings) plus fees
+    pub fn net_asset_value(&self, market: &Market) -> Amount {
+        let capital = match (market.status, market.outcome) {
+            (MarketStatus::Resolved, Some(outcome)) => {
+                let (seed, pool) = match outcome {
+                    Outcome::Up => (self.up_seed, market.up_pool),
+                    Outcome::Down => (self.down_seed, market.down_pool),
+                };
+                market.payout_for(seed, pool)
+            }
+            _ => self.up_seed.saturating_add(self.down_seed),
+        };
+        capital.saturating_add(self.fees)
+    }
+
+    /// Shares minted for a deposit, given the pool's value before it
+    pub fn shares_for(&self, deposit: Amount, nav: Amount) -> Amount {
+        if self.total_shares.is_zero() || nav.is_zero() {
+            return deposit;
+        }
+        Amount::from_attos(mul_div(
+            u128::from(deposit),
+            u128::from(self.total_shares),
+            u128::from(nav),
+        ))
+    }
+
+    /// Pro-rata part of `total` owned by `shares`
+    pub fn portion(&self, total: Amount, shares: Amount) -> Amount {
+        Amount::from_attos(mul_div(
+            u128::from(total),
+            u128::from(shares),
+            u128::from(self.total_shares),
+        ))
+    }
+}
+
+/// `a * b / c` rounded down, without intermediate overflow.
+/// Returns 0 when `c` is 0 and saturates if the result exceeds `u128`.
+pub fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+    const MASK: u128 = u64::MAX as u128;
+
+    if c == 0 {
+        return 0;
+    }
+    if let Some(product) = a.checked_mul(b) {
+        return product / c;
+    }
+
+    // 256-bit product as (hi, lo)
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let mid = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (lo_lo & MASK) | (mid << 64);
+    let hi = a_hi * b_hi + (hi_lo >> 64) + (lo_hi >> 64) + (mid >> 64);
+
+    if hi >= c {
+        return u128::MAX;
+    }
+
+    // Binary long division; the remainder always stays below `c`
+    let mut rem = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || rem >= c {
+            rem = rem.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    quotient
+}
+
+impl LimitOrder {
+    pub fn remaining(&self) -> Amount {
+        self.quantity.saturating_sub(self.filled)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.status == OrderStatus::Open
+    }
+
+    /// Whether this resting order can trade with `taker`: same outcome, opposite
+    /// side, another owner, and prices that cross
+    pub fn can_fill(&self, taker: &LimitOrder) -> bool {
+        self.outcome == taker.outcome
+            && self.side != taker.side
+            && self.owner != taker.owner
+            && match taker.side {
+                OrderSide::Buy => self.price_bps <= taker.price_bps,
+                OrderSide::Sell => self.price_bps >= taker.price_bps,
+            }
+    }
+}
+
+/// Kelly-optimal share of the bankroll, in basis points, for a bet on a side holding
+/// `odds_bps` of the pool that wins with `win_probability_bps`, ignoring fees.
+/// The net odds are `b = (1 - s) / s` for a pool share `s`, so `f* = (b·p - q) / b`
+/// simplifies to `(p - s) / (1 - s)`. Zero when the bet has no edge.
+pub fn kelly_bps(odds_bps: u32, win_probability_bps: u32) -> u32 {
+    let probability = win_probability_bps.min(PRICE_BPS_SCALE);
+    if probability <= odds_bps || odds_bps >= PRICE_BPS_SCALE {
+        return 0;
+    }
+    mul_div(
+        u128::from(probability - odds_bps),
+        u128::from(PRICE_BPS_SCALE),
+        u128::from(PRICE_BPS_SCALE - odds_bps),
+    ) as u32
+}
+
+/// Tokens owed for `quantity` of stake at `price_bps`
+pub fn order_cost(quantity: Amount, price_bps: u32) -> Amount {
+    Amount::from_attos(mul_div(
+        u128::from(quantity),
+        u128::from(price_bps),
+        u128::from(PRICE_BPS_SCALE),
+    ))
+}
+
+impl SignedAmount {
+    pub const ZERO: SignedAmount = SignedAmount {
+        magnitude: Amount::ZERO,
+        negative: false,
+    };
+
+    /// `proceeds - cost`, which may be negative
+    pub fn difference(proceeds: Amount, cost: Amount) -> Self {
+        if proceeds >= cost {
+            SignedAmount {
+                magnitude: proceeds.saturating_sub(cost),
+                negative: false,
+            }
+        } else {
+            SignedAmount {
+                magnitude: cost.saturating_sub(proceeds),
+                negative: true,
+            }
+        }
+    }
+
+    pub fn saturating_add(self, other: SignedAmount) -> Self {
+        if self.negative == other.negative {
+            return SignedAmount {
+                magnitude: self.magnitude.saturating_add(other.magnitude),
+                negative: self.negative,
+            };
+        }
+        let (positive, negative) = if self.negative {
+            (other.magnitude, self.magnitude)
+        } else {
+            (self.magnitude, other.magnitude)
+        };
+        SignedAmount::difference(positive, negative)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative && !self.magnitude.is_zero()
+    }
+
+    pub fn negate(self) -> Self {
+        SignedAmount {
+            magnitude: self.magnitude,
+            negative: !self.negative && !self.magnitude.is_zero(),
+        }
+    }
+}
+
+impl PartialEq for SignedAmount {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for SignedAmount {}
+
+impl PartialOrd for SignedAmount {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SignedAmount {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (self.is_negative(), other.is_negative()) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (false, true) => std::cmp::Ordering::Greater,
+            (true, false) => std::cmp::Ordering::Less,
+        }
+    }
+}
+
+impl AgentStrategy {
+    /// Decide a bet on a market from its pools: the side and the share of
+    /// `balance` to stake. `None` if the strategy passes on the market.
+    ///
+    /// - Conservative: backs the favourite with 1% only when it holds 70%+ of the pool
+    /// - Moderate: backs the favourite with 2% when it holds 55%+ of the pool
+    /// - Aggressive: backs the underdog with 5% when it holds 40% or less
+    /// - Contrarian: bets against any majority with 3%
+    /// - AIAnalysis: bets only on off-chain analysis, never from the crank
+    pub fn plan(
+        &self,
+        up_pool: Amount,
+        down_pool: Amount,
+        balance: Amount,
+    ) -> Option<(Outcome, Amount)> {
+        let total = up_pool.saturating_add(down_pool);
+        if total.is_zero() {
+            return None;
+        }
+
+        let up_share_bps = mul_div(
+            u128::from(up_pool),
+            u128::from(PRICE_BPS_SCALE),
+            u128::from(total),
+        ) as u32;
+        let (favourite, favourite_bps) = if up_share_bps >= PRICE_BPS_SCALE / 2 {
+            (Outcome::Up, up_share_bps)
+        } else {
+            (Outcome::Down, PRICE_BPS_SCALE - up_share_bps)
+        };
+        let underdog = match favourite {
+            Outcome::Up => Outcome::Down,
+            Outcome::Down => Outcome::Up,
+        };
+
+        let (side, percent) = match self {
+            AgentStrategy::Conservative if favourite_bps >= 7_000 => (favourite, 1),
+            AgentStrategy::Moderate if favourite_bps >= 5_500 => (favourite, 2),
+            AgentStrategy::Aggressive if favourite_bps >= 6_000 => (underdog, 5),
+            AgentStrategy::Contrarian if favourite_bps > 5_000 => (underdog, 3),
+            _ => return None,
+        };
+
+        Some((side, Amount::from_attos(u128::from(balance) * percent / 100)))
+    }
+}
+
+impl StrategyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for bps in [self.min_odds_bps, self.max_odds_bps, self.kelly_fraction_bps]
+            .into_iter()
+            .flatten()
+        {
+            if bps > PRICE_BPS_SCALE {
+                return Err(format!("{} bps is above 100%", bps));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_odds_bps, self.max_odds_bps) {
+            if min > max {
+                return Err("Minimum odds exceed maximum odds".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Largest stake `agent` may put into one more bet, at most `amount`, on a side
+    /// holding `odds_bps` of the pool that wins with `win_probability_bps`
+    pub fn cap(
+        &self,
+        agent: &AIAgent,
+        amount: Amount,
+        odds_bps: u32,
+        win_probability_bps: Option<u32>,
+        current_time: Timestamp,
+    ) -> Amount {
+        let mut capped = amount.min(agent.balance);
+        if let Some(max_bet) = self.max_bet {
+            capped = capped.min(max_bet);
+        }
+        if let Some(max_exposure) = self.max_exposure {
+            capped = capped.min(max_exposure.saturating_sub(agent.open_stake));
+        }
+        if let Some(fraction_bps) = self.kelly_fraction_bps {
+            let kelly_bps = win_probability_bps.map_or(0, |p| kelly_bps(odds_bps, p));
+            capped = capped.min(Self::kelly_cap(agent.balance, kelly_bps, fraction_bps));
+        }
+        if let Some(max_daily_spend) = self.max_daily_spend {
+            let spent = agent.spent_on(current_time);
+            capped = capped.min(max_daily_spend.saturating_sub(spent));
+        }
+        capped
+    }
+
+    /// Check a bet of `amount` on the side of a `category` market holding
+    /// `odds_bps` of its pool, which the agent expects to win with
+    /// `win_probability_bps`. `new_position` is whether the agent has no stake
+    /// in the market yet.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check(
+        &self,
+        agent: &AIAgent,
+        category: MarketCategory,
+        odds_bps: u32,
+        win_probability_bps: Option<u32>,
+        amount: Amount,
+        new_position: bool,
+        current_time: Timestamp,
+    ) -> Result<(), String> {
+        if win_probability_bps.is_some_and(|p| p > PRICE_BPS_SCALE) {
+            return Err("Win probability is above 100%".to_string());
+        }
+        if self.kelly_fraction_bps.is_some() && win_probability_bps.is_none() {
+            return Err("Kelly sizing needs a win probability for the bet".to_string());
+        }
+        if !self.categories.is_empty() && !self.categories.contains(&category) {
+            return Err(format!("Agent does not bet on {} markets", category.as_str()));
+        }
+        if self.min_odds_bps.is_some_and(|min| odds_bps < min)
+            || self.max_odds_bps.is_some_and(|max| odds_bps > max)
+        {
+            return Err(format!("Odds of {} bps are outside the agent's range", odds_bps));
+        }
+        if let Some(stop_loss) = self.stop_loss {
+            if agent.total_profit.is_negative() && agent.total_profit.magnitude >= stop_loss {
+                return Err("Agent has hit its stop-loss".to_string());
+            }
+        }
+        if let Some(max_open_positions) = self.max_open_positions {
+            if new_position && agent.open_positions >= max_open_positions {
+                return Err(format!(
+                    "Agent already holds {} open positions",
+                    agent.open_positions
+                ));
+            }
+        }
+        let allowed = self.cap(agent, amount, odds_bps, win_probability_bps, current_time);
+        if amount > allowed {
+            return Err(format!(
+                "Bet of {} exceeds the {} the agent's limits allow",
+                amount, allowed
+            ));
+        }
+        Ok(())
+    }
+
+    /// `fraction_bps` of a Kelly stake of `kelly_bps` of the balance
+    fn kelly_cap(balance: Amount, kelly_bps: u32, fraction_bps: u32) -> Amount {
+        Amount::from_attos(mul_div(
+            u128::from(balance),
+            u128::from(kelly_bps) * u128::from(fraction_bps),
+            u128::from(PRICE_BPS_SCALE) * u128::from(PRICE_BPS_SCALE),
+        ))
+    }
+}
+
+#[ComplexObject]
+impl AIAgent {
+    /// Value of the agent's vault
+    async fn nav(&self) -> Amount {
+        self.net_asset_value()
+    }
+
+    /// Tokens one whole share redeems for
+    async fn share_price(&self) -> Amount {
+        self.value_of(Amount::ONE)
+    }
+
+    /// Win rate, in percent
+    async fn accuracy(&self) -> f32 {
+        self.win_rate()
+    }
+
+    /// Share of stake on the winning outcome, in percent
+    async fn stake_weighted_accuracy(&self) -> f64 {
+        percent_of(self.winning_stake, self.total_staked)
+    }
+
+    /// `total_profit / total_staked`, in percent
+    async fn roi(&self) -> f64 {
+        let roi = percent_of(self.total_profit.magnitude, self.total_staked);
+        if self.total_profit.is_negative() {
+            -roi
+        } else {
+            roi
+        }
+    }
+
+    /// Mean return per settled position (PnL / stake)
+    async fn mean_return(&self) -> f64 {
+        self.mean_return_bps() / f64::from(PRICE_BPS_SCALE)
+    }
+
+    /// Mean return over its standard deviation; zero until two positions settle
+    async fn sharpe_ratio(&self) -> f64 {
+        if self.total_bets < 2 {
+            return 0.0;
+        }
+        let count = self.total_bets as f64;
+        let mean = self.mean_return_bps();
+        let variance =
+            (self.return_sq_sum_bps as f64 - mean * self.return_sum_bps as f64) / (count - 1.0);
+        if variance > 0.0 {
+            mean / variance.sqrt()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// `part / whole`, in percent
+fn percent_of(part: Amount, whole: Amount) -> f64 {
+    if whole.is_zero() {
+        return 0.0;
+    }
+    u128::from(part) as f64 / u128::from(whole) as f64 * 100.0
 }
 
 impl AIAgent {
+    /// Value of the vault: free balance plus open positions at cost
+    pub fn net_asset_value(&self) -> Amount {
+        self.balance.saturating_add(self.open_stake)
+    }
+
+    /// Shares minted for a deposit of `amount` at the current NAV. Zero if the
+    /// existing shares have lost all their value.
+    pub fn shares_for(&self, amount: Amount) -> Amount {
+        if self.total_shares.is_zero() {
+            return amount;
+        }
+        let nav = self.net_asset_value();
+        if nav.is_zero() {
+            return Amount::ZERO;
+        }
+        Amount::from_attos(mul_div(
+            u128::from(amount),
+            u128::from(self.total_shares),
+            u128::from(nav),
+        ))
+    }
+
+    /// Tokens `shares` redeem for at the current NAV
+    pub fn value_of(&self, shares: Amount) -> Amount {
+        if self.total_shares.is_zero() {
+            return Amount::ZERO;
+        }
+        Amount::from_attos(mul_div(
+            u128::from(shares),
+            u128::from(self.net_asset_value()),
+            u128::from(self.total_shares),
+        ))
+    }
+
+    /// Fee owed to the owner after `total_profit` moved past `previous_peak`.
+    /// The high-water mark is never below zero: recovering losses earns no fee.
+    pub fn performance_fee(&self, previous_peak: SignedAmount) -> Amount {
+        let high_water_mark = previous_peak.max(SignedAmount::ZERO);
+        let gain = self.total_profit.saturating_add(high_water_mark.negate());
+        if gain.is_negative() {
+            return Amount::ZERO;
+        }
+        Amount::from_attos(mul_div(
+            u128::from(gain.magnitude),
+            u128::from(self.performance_fee_bps),
+            u128::from(PRICE_BPS_SCALE),
+        ))
+    }
+
+    /// Take a performance fee paid out of the vault off the profit. Fees are only
+    /// charged at a new peak, so the peak comes down with it.
+    pub fn record_fee(&mut self, fee: Amount) {
+        let paid = SignedAmount::difference(Amount::ZERO, fee);
+        self.total_profit = self.total_profit.saturating_add(paid);
+        self.peak_profit = self.peak_profit.saturating_add(paid);
+    }
+
+    /// Whether `account` may act for the agent
+    pub fn can_operate(&self, account: &AccountOwner) -> bool {
+        self.owner == *account || self.operators.contains(account)
+    }
+
+    /// Stake placed during the spend window containing `current_time`
+    pub fn spent_on(&self, current_time: Timestamp) -> Amount {
+        if current_time.micros() / AGENT_SPEND_WINDOW_MICROS == self.spend_day {
+            self.spent_today
+        } else {
+            Amount::ZERO
+        }
+    }
+
+    /// Count a new stake of `amount` towards the daily spend
+    pub fn record_spend(&mut self, amount: Amount, current_time: Timestamp) {
+        self.spent_today = self.spent_on(current_time).saturating_add(amount);
+        self.spend_day = current_time.micros() / AGENT_SPEND_WINDOW_MICROS;
+    }
+
+    /// Mean per-position return, in basis points
+    fn mean_return_bps(&self) -> f64 {
+        if self.total_bets == 0 {
+            return 0.0;
+        }
+        self.return_sum_bps as f64 / self.total_bets as f64
+    }
+
     pub fn win_rate(&self) -> f32 {
         if self.total_bets == 0 {
             return 0.0;
@@ -216,14 +1021,458 @@ impl AIAgent {
         (self.wins as f32 / self.total_bets as f32) * 100.0
     }
 
-    pub fn update_stats(&mut self, won: bool, profit: Amount) {
+    /// Record a settled position: its total `stake`, the part of it on the
+    /// winning outcome, and its realized `pnl`
+    pub fn update_stats(
+        &mut self,
+        won: bool,
+        stake: Amount,
+        winning_stake: Amount,
+        pnl: SignedAmount,
+    ) {
         self.total_bets += 1;
         if won {
             self.wins += 1;
         } else {
             self.losses += 1;
         }
-        self.total_profit = self.total_profit.saturating_add(profit);
-        self.accuracy = self.win_rate();
+
+        self.total_profit = self.total_profit.saturating_add(pnl);
+        self.total_staked = self.total_staked.saturating_add(stake);
+        self.winning_stake = self.winning_stake.saturating_add(winning_stake);
+
+        if self.total_profit > self.peak_profit {
+            self.peak_profit = self.total_profit;
+        }
+        let drawdown = self.peak_profit.saturating_add(self.total_profit.negate());
+        if drawdown.magnitude > self.max_drawdown {
+            self.max_drawdown = drawdown.magnitude;
+        }
+
+        // Sums of per-position returns; the service derives the mean and Sharpe ratio
+        let return_bps = mul_div(
+            u128::from(pnl.magnitude),
+            u128::from(PRICE_BPS_SCALE),
+            u128::from(stake),
+        );
+        self.return_sq_sum_bps = self
+            .return_sq_sum_bps
+            .saturating_add(return_bps.saturating_mul(return_bps));
+        let return_bps = i128::try_from(return_bps).unwrap_or(i128::MAX);
+        let signed_return_bps = if pnl.is_negative() {
+            -return_bps
+        } else {
+            return_bps
+        };
+        self.return_sum_bps = self.return_sum_bps.saturating_add(signed_return_bps);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(tokens: u128, negative: bool) -> SignedAmount {
+        SignedAmount {
+            magnitude: Amount::from_tokens(tokens),
+            negative,
+        }
+    }
+
+    #[test]
+    fn mul_div_matches_plain_arithmetic() {
+        assert_eq!(mul_div(6, 7, 3), 14);
+        assert_eq!(mul_div(10, 1, 3), 3);
+        assert_eq!(mul_div(0, 5, 7), 0);
+        assert_eq!(mul_div(5, 7, 0), 0);
+    }
+
+    #[test]
+    fn mul_div_survives_overflowing_products() {
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), u128::MAX);
+        assert_eq!(mul_div(u128::MAX, 4, 8), u128::MAX / 2);
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 120), 1 << 80);
+        assert_eq!(mul_div(u128::MAX, 3, 2), u128::MAX);
+    }
+
+    #[test]
+    fn lp_shares_track_pool_value() {
+        let mut pool = LiquidityPool::new(0);
+        assert_eq!(
+            pool.shares_for(Amount::from_tokens(100), Amount::ZERO),
+            Amount::from_tokens(100)
+        );
+
+        // The pool doubled in value: a new deposit buys half as many shares
+        pool.total_shares = Amount::from_tokens(100);
+        let shares = pool.shares_for(Amount::from_tokens(100), Amount::from_tokens(200));
+        assert_eq!(shares, Amount::from_tokens(50));
+
+        pool.total_shares = Amount::from_tokens(150);
+        assert_eq!(
+            pool.portion(Amount::from_tokens(300), Amount::from_tokens(50)),
+            Amount::from_tokens(100)
+        );
+    }
+
+    #[test]
+    fn signed_amount_difference_carries_the_sign() {
+        let gain = SignedAmount::difference(Amount::from_tokens(12), Amount::from_tokens(10));
+        assert_eq!(gain, signed(2, false));
+
+        let loss = SignedAmount::difference(Amount::from_tokens(10), Amount::from_tokens(12));
+        assert_eq!(loss, signed(2, true));
+        assert!(loss.is_negative());
+
+        let even = SignedAmount::difference(Amount::from_tokens(10), Amount::from_tokens(10));
+        assert_eq!(even, SignedAmount::ZERO);
+    }
+
+    #[test]
+    fn signed_amount_addition_crosses_zero() {
+        assert_eq!(
+            signed(5, false).saturating_add(signed(3, false)),
+            signed(8, false)
+        );
+        assert_eq!(
+            signed(5, true).saturating_add(signed(3, true)),
+            signed(8, true)
+        );
+        assert_eq!(
+            signed(5, false).saturating_add(signed(8, true)),
+            signed(3, true)
+        );
+        assert_eq!(
+            signed(5, true).saturating_add(signed(8, false)),
+            signed(3, false)
+        );
+        assert_eq!(
+            signed(5, true).saturating_add(signed(5, false)),
+            SignedAmount::ZERO
+        );
+    }
+
+    #[test]
+    fn signed_amount_orders_losses_below_gains() {
+        let mut values = vec![
+            signed(1, false),
+            signed(3, true),
+            SignedAmount::ZERO,
+            signed(1, true),
+        ];
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                signed(3, true),
+                signed(1, true),
+                SignedAmount::ZERO,
+                signed(1, false)
+            ]
+        );
+        assert_eq!(signed(4, false).negate(), signed(4, true));
+    }
+
+    #[test]
+    fn signed_amount_zero_has_one_representation() {
+        let negated = SignedAmount::ZERO.negate();
+        assert!(!negated.negative);
+        assert_eq!(negated, SignedAmount::ZERO);
+
+        let negative_zero = SignedAmount {
+            magnitude: Amount::ZERO,
+            negative: true,
+        };
+        assert_eq!(negative_zero, SignedAmount::ZERO);
+        assert_eq!(
+            negative_zero.cmp(&SignedAmount::ZERO),
+            std::cmp::Ordering::Equal
+        );
+    }
+
+    fn agent() -> AIAgent {
+        AIAgent {
+            id: 0,
+            owner: AccountOwner::Address20([1; 20]),
+            operators: Vec::new(),
+            name: "agent".to_string(),
+            strategy: AgentStrategy::Moderate,
+            total_bets: 0,
+            wins: 0,
+            losses: 0,
+            total_profit: SignedAmount::ZERO,
+            total_staked: Amount::ZERO,
+            winning_stake: Amount::ZERO,
+            peak_profit: SignedAmount::ZERO,
+            max_drawdown: Amount::ZERO,
+            return_sum_bps: 0,
+            return_sq_sum_bps: 0,
+            active: true,
+            balance: Amount::ZERO,
+            open_positions: 0,
+            open_stake: Amount::ZERO,
+            config: StrategyConfig::default(),
+            spend_day: 0,
+            spent_today: Amount::ZERO,
+            total_shares: Amount::ZERO,
+            queued_shares: Amount::ZERO,
+            performance_fee_bps: 0,
+        }
+    }
+
+    #[test]
+    fn agent_stats_accumulate_in_fixed_point() {
+        let mut agent = agent();
+        let stake = Amount::from_tokens(100);
+        agent.update_stats(true, stake, stake, signed(50, false));
+        agent.update_stats(false, stake, Amount::ZERO, signed(100, true));
+        agent.update_stats(true, stake, stake, signed(20, false));
+
+        assert_eq!((agent.wins, agent.losses), (2, 1));
+        assert_eq!(agent.total_profit, signed(30, true));
+        assert_eq!(agent.total_staked, Amount::from_tokens(300));
+        assert_eq!(agent.winning_stake, Amount::from_tokens(200));
+        assert_eq!(agent.peak_profit, signed(50, false));
+        assert_eq!(agent.max_drawdown, Amount::from_tokens(100));
+
+        // Returns of +50%, -100% and +20%
+        assert_eq!(agent.return_sum_bps, 5_000 - 10_000 + 2_000);
+        assert_eq!(
+            agent.return_sq_sum_bps,
+            5_000 * 5_000 + 10_000 * 10_000 + 2_000 * 2_000
+        );
+        assert_eq!(agent.mean_return_bps(), -1_000.0);
+    }
+
+    #[test]
+    fn kelly_stake_grows_with_the_edge() {
+        // No edge at or below the market's own odds
+        assert_eq!(kelly_bps(4_000, 4_000), 0);
+        assert_eq!(kelly_bps(4_000, 3_000), 0);
+        // Even odds and a 60% estimate: f* = (0.6 - 0.5) / 0.5 = 20%
+        assert_eq!(kelly_bps(5_000, 6_000), 2_000);
+        // Underdog at 25% with a 40% estimate: f* = 0.15 / 0.75 = 20%
+        assert_eq!(kelly_bps(2_500, 4_000), 2_000);
+        // An empty side pays everything: stake the win probability
+        assert_eq!(kelly_bps(0, 7_000), 7_000);
+        assert_eq!(kelly_bps(10_000, 10_000), 0);
+    }
+
+    #[test]
+    fn strategies_plan_bets_from_the_pools() {
+        use AgentStrategy::*;
+        use Outcome::{Down, Up};
+
+        let tokens = Amount::from_tokens;
+        let cases = [
+            // A 75% favourite
+            (Conservative, 75, 25, Some((Up, 10))),
+            (Moderate, 75, 25, Some((Up, 20))),
+            (Aggressive, 75, 25, Some((Down, 50))),
+            (Contrarian, 75, 25, Some((Down, 30))),
+            (AIAnalysis, 75, 25, None),
+            // Conservative waits for lopsided odds, on either side
+            (Conservative, 60, 40, None),
+            (Conservative, 30, 70, Some((Down, 10))),
+            (Moderate, 40, 60, Some((Down, 20))),
+            (Aggressive, 55, 45, None),
+            // An even or empty market has no favourite to back or fade
+            (Moderate, 50, 50, None),
+            (Contrarian, 50, 50, None),
+            (Contrarian, 0, 0, None),
+        ];
+
+        for (strategy, up, down, expected) in cases {
+            let plan = strategy.plan(tokens(up), tokens(down), tokens(1_000));
+            let expected = expected.map(|(side, stake)| (side, tokens(stake)));
+            assert_eq!(plan, expected, "{:?} on {}/{}", strategy, up, down);
+        }
+    }
+
+    #[test]
+    fn risk_budget_caps_bet_size_and_exposure() {
+        let tokens = Amount::from_tokens;
+        let now = Timestamp::from(0);
+        let mut agent = agent();
+        agent.balance = tokens(1_000);
+        let config = StrategyConfig {
+            max_bet: Some(tokens(50)),
+            max_exposure: Some(tokens(100)),
+            ..Default::default()
+        };
+        let cap = |agent: &AIAgent| config.cap(agent, tokens(200), 5_000, None, now);
+
+        assert_eq!(cap(&agent), tokens(50));
+        agent.open_stake = tokens(70);
+        assert_eq!(cap(&agent), tokens(30));
+        agent.open_stake = tokens(100);
+        assert_eq!(cap(&agent), Amount::ZERO);
+
+        // Never more than the agent holds
+        agent.open_stake = Amount::ZERO;
+        agent.balance = tokens(20);
+        assert_eq!(cap(&agent), tokens(20));
+    }
+
+    #[test]
+    fn strategy_config_check_enforces_each_limit() {
+        let now = Timestamp::from(0);
+        let mut agent = agent();
+        agent.balance = Amount::from_tokens(1_000);
+        let bet = Amount::from_tokens(10);
+        let check = |config: &StrategyConfig, agent: &AIAgent, odds_bps, amount| {
+            config.check(
+                agent,
+                MarketCategory::Crypto,
+                odds_bps,
+                None,
+                amount,
+                true,
+                now,
+            )
+        };
+
+        assert!(check(&StrategyConfig::default(), &agent, 5_000, bet).is_ok());
+
+        let categories = StrategyConfig {
+            categories: vec![MarketCategory::Sports],
+            ..Default::default()
+        };
+        assert!(check(&categories, &agent, 5_000, bet).is_err());
+
+        let odds = StrategyConfig {
+            min_odds_bps: Some(4_000),
+            max_odds_bps: Some(6_000),
+            ..Default::default()
+        };
+        assert!(check(&odds, &agent, 5_000, bet).is_ok());
+        assert!(check(&odds, &agent, 3_999, bet).is_err());
+        assert!(check(&odds, &agent, 6_001, bet).is_err());
+
+        let max_bet = StrategyConfig {
+            max_bet: Some(bet),
+            ..Default::default()
+        };
+        assert!(check(&max_bet, &agent, 5_000, bet).is_ok());
+        assert!(check(&max_bet, &agent, 5_000, bet.saturating_add(bet)).is_err());
+
+        let open_positions = StrategyConfig {
+            max_open_positions: Some(2),
+            ..Default::default()
+        };
+        agent.open_positions = 2;
+        assert!(check(&open_positions, &agent, 5_000, bet).is_err());
+        let existing_position =
+            open_positions.check(&agent, MarketCategory::Crypto, 5_000, None, bet, false, now);
+        assert!(existing_position.is_ok());
+
+        let daily_spend = StrategyConfig {
+            max_daily_spend: Some(Amount::from_tokens(15)),
+            ..Default::default()
+        };
+        agent.record_spend(bet, now);
+        assert!(check(&daily_spend, &agent, 5_000, Amount::from_tokens(5)).is_ok());
+        assert!(check(&daily_spend, &agent, 5_000, bet).is_err());
+
+        let stop_loss = StrategyConfig {
+            stop_loss: Some(Amount::from_tokens(100)),
+            ..Default::default()
+        };
+        agent.total_profit = signed(99, true);
+        assert!(check(&stop_loss, &agent, 5_000, bet).is_ok());
+        agent.total_profit = signed(100, true);
+        assert!(check(&stop_loss, &agent, 5_000, bet).is_err());
+    }
+
+    #[test]
+    fn strategy_config_sizes_bets_by_fractional_kelly() {
+        let now = Timestamp::from(0);
+        let mut agent = agent();
+        agent.balance = Amount::from_tokens(1_000);
+        let half_kelly = StrategyConfig {
+            kelly_fraction_bps: Some(5_000),
+            ..Default::default()
+        };
+
+        // Full Kelly at even odds with a 60% estimate is 20%, so half Kelly is 10%
+        let cap = half_kelly.cap(&agent, Amount::from_tokens(500), 5_000, Some(6_000), now);
+        assert_eq!(cap, Amount::from_tokens(100));
+
+        let check = |probability, amount| {
+            half_kelly.check(
+                &agent,
+                MarketCategory::Crypto,
+                5_000,
+                probability,
+                amount,
+                true,
+                now,
+            )
+        };
+        assert!(check(Some(6_000), Amount::from_tokens(100)).is_ok());
+        assert!(check(Some(6_000), Amount::from_tokens(101)).is_err());
+        assert!(check(Some(5_000), Amount::from_tokens(1)).is_err());
+        assert!(check(None, Amount::from_tokens(1)).is_err());
+    }
+
+    #[test]
+    fn vault_shares_follow_net_asset_value() {
+        let tokens = Amount::from_tokens;
+        let mut agent = agent();
+        assert_eq!(agent.shares_for(tokens(100)), tokens(100));
+        assert_eq!(agent.value_of(tokens(100)), Amount::ZERO);
+
+        agent.total_shares = tokens(100);
+        agent.balance = tokens(150);
+        agent.open_stake = tokens(50);
+        assert_eq!(agent.net_asset_value(), tokens(200));
+
+        // Each share is worth 2 tokens
+        assert_eq!(agent.shares_for(tokens(100)), tokens(50));
+        assert_eq!(agent.value_of(tokens(25)), tokens(50));
+        assert_eq!(agent.value_of(agent.total_shares), agent.net_asset_value());
+
+        // Shares that lost all their value mint nothing new
+        agent.balance = Amount::ZERO;
+        agent.open_stake = Amount::ZERO;
+        assert_eq!(agent.shares_for(tokens(100)), Amount::ZERO);
+    }
+
+    #[test]
+    fn performance_fee_applies_only_above_the_previous_peak() {
+        let tokens = Amount::from_tokens;
+        let mut agent = agent();
+        agent.performance_fee_bps = 2_000;
+
+        agent.total_profit = signed(150, false);
+        assert_eq!(agent.performance_fee(signed(100, false)), tokens(10));
+        // Climbing back from a loss is not profit above the high-water mark
+        assert_eq!(agent.performance_fee(signed(50, true)), tokens(30));
+
+        agent.total_profit = signed(80, false);
+        assert_eq!(agent.performance_fee(signed(100, false)), Amount::ZERO);
+        assert_eq!(agent.performance_fee(signed(80, false)), Amount::ZERO);
+
+        agent.total_profit = signed(20, true);
+        assert_eq!(agent.performance_fee(signed(50, true)), Amount::ZERO);
+    }
+
+    #[test]
+    fn performance_fee_comes_off_profit_and_peak() {
+        let tokens = Amount::from_tokens;
+        let mut agent = agent();
+        agent.performance_fee_bps = 2_000;
+        agent.update_stats(true, tokens(100), tokens(100), signed(50, false));
+
+        let fee = agent.performance_fee(SignedAmount::ZERO);
+        assert_eq!(fee, tokens(10));
+        agent.record_fee(fee);
+        assert_eq!(agent.total_profit, signed(40, false));
+        assert_eq!(agent.peak_profit, signed(40, false));
+        assert_eq!(agent.max_drawdown, Amount::ZERO);
+
+        // The next fee is only owed on profit above what is left after this one
+        agent.update_stats(true, tokens(100), tokens(100), signed(5, false));
+        assert_eq!(agent.performance_fee(signed(40, false)), tokens(1));
     }
 }
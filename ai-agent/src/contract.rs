@@ -3,14 +3,23 @@
 mod state;
 
 use self::state::AIAgentState;
-use ai_agent::{AIAgentOperation, AIAgentParameters, AIAgentResponse};
+use ai_agent::{
+    AIAgentOperation, AIAgentParameters, AIAgentResponse, CopyLeader, CopySubscription,
+    WithdrawalRequest,
+};
+use async_graphql::Request;
 use linera_sdk::{
-    linera_base_types::Amount,
+    linera_base_types::{
+        AccountOwner, Amount, GenericApplicationId, StreamName, StreamUpdate, Timestamp,
+    },
     abi::WithContractAbi,
     views::{RootView, View},
     Contract, ContractRuntime,
 };
+use market::{MarketEvent, MarketOperation, MarketResponse, MARKET_STREAM_NAME};
 use oracle_ai_abi::*;
+use serde::Deserialize;
+use token::{TokenOperation, TokenResponse};
 
 pub struct AIAgentContract {
     state: AIAgentState,
@@ -42,22 +51,49 @@ impl Contract for AIAgentContract {
 
     async fn execute_operation(&mut self, operation: Self::Operation) -> Self::Response {
         match operation {
-            AIAgentOperation::CreateAgent { name, strategy } => {
+            AIAgentOperation::CreateAgent {
+                name,
+                strategy,
+                performance_fee_bps,
+            } => {
                 let creator = match self.runtime.authenticated_signer() {
                     Some(s) => s,
                     None => return AIAgentResponse::Error("Must be authenticated".to_string()),
                 };
 
+                if performance_fee_bps > MAX_PERFORMANCE_FEE_BPS {
+                    return AIAgentResponse::Error(format!(
+                        "Performance fee cannot exceed {} bps",
+                        MAX_PERFORMANCE_FEE_BPS
+                    ));
+                }
+
                 let agent = AIAgent {
                     id: 0, // Will be set by create_agent
+                    owner: creator,
+                    operators: Vec::new(),
                     name,
                     strategy,
                     total_bets: 0,
                     wins: 0,
                     losses: 0,
-                    total_profit: Amount::ZERO,
-                    accuracy: 0.0,
+                    total_profit: SignedAmount::ZERO,
+                    total_staked: Amount::ZERO,
+                    winning_stake: Amount::ZERO,
+                    peak_profit: SignedAmount::ZERO,
+                    max_drawdown: Amount::ZERO,
+                    return_sum_bps: 0,
+                    return_sq_sum_bps: 0,
                     active: true,
+                    balance: Amount::ZERO,
+                    open_positions: 0,
+                    open_stake: Amount::ZERO,
+                    config: StrategyConfig::default(),
+                    spend_day: 0,
+                    spent_today: Amount::ZERO,
+                    total_shares: Amount::ZERO,
+                    queued_shares: Amount::ZERO,
+                    performance_fee_bps,
                 };
 
                 match self.state.create_agent(agent).await {
@@ -71,48 +107,61 @@ impl Contract for AIAgentContract {
                 market_id,
                 prediction,
                 amount,
+                win_probability_bps,
             } => {
                 // Get agent
-                let mut agent = match self.state.get_agent(agent_id).await {
+                let agent = match self.state.get_agent(agent_id).await {
                     Ok(a) => a,
                     Err(e) => return AIAgentResponse::Error(e),
                 };
 
+                if let Err(e) = self.check_operator(&agent) {
+                    return AIAgentResponse::Error(e);
+                }
+
                 if !agent.active {
                     return AIAgentResponse::Error("Agent is not active".to_string());
                 }
 
-                // Call market app to place bet
-                let market_app_id = self.runtime.application_parameters().market_app;
-                let call = market::MarketOperation::PlaceBet { 
-                    market_id, 
-                    prediction, 
-                    amount 
+                let market = match self.market_snapshot(market_id) {
+                    Ok(m) => m,
+                    Err(e) => return AIAgentResponse::Error(e),
                 };
 
-                self.runtime.call_application(true, market_app_id, &call);
-
-                // Update agent stats
-                agent.total_bets += 1;
-
-                if let Err(e) = self.state.update_agent(agent).await {
+                if let Err(e) = self
+                    .place_agent_bet(agent, &market, prediction, amount, win_probability_bps)
+                    .await
+                {
                     return AIAgentResponse::Error(e);
                 }
 
+                // Followers size their copies with the leader's estimate. The bet is
+                // already placed, so only a storage failure may stop here, and it must
+                // reject the whole block
+                let leader = CopyLeader::Agent(agent_id);
+                self.mirror_bet(leader, market_id, prediction, amount, win_probability_bps)
+                    .await
+                    .expect("Failed to mirror bet");
                 AIAgentResponse::Ok
             }
 
-            AIAgentOperation::UpdateStats {
-                agent_id,
-                won,
-                profit,
-            } => {
+            AIAgentOperation::SetStrategyConfig { agent_id, config } => {
                 let mut agent = match self.state.get_agent(agent_id).await {
                     Ok(a) => a,
                     Err(e) => return AIAgentResponse::Error(e),
                 };
 
-                agent.update_stats(won, profit);
+                if self.runtime.authenticated_signer() != Some(agent.owner) {
+                    return AIAgentResponse::Error(
+                        "Only the owner can change the strategy config".to_string(),
+                    );
+                }
+
+                if let Err(e) = config.validate() {
+                    return AIAgentResponse::Error(e);
+                }
+
+                agent.config = config;
 
                 match self.state.update_agent(agent).await {
                     Ok(_) => AIAgentResponse::Ok,
@@ -120,12 +169,21 @@ impl Contract for AIAgentContract {
                 }
             }
 
+            AIAgentOperation::RunAgent { agent_id } => match self.run_agent(agent_id).await {
+                Ok(placed) => AIAgentResponse::BetsPlaced(placed),
+                Err(e) => AIAgentResponse::Error(e),
+            },
+
             AIAgentOperation::ToggleAgent { agent_id } => {
                 let mut agent = match self.state.get_agent(agent_id).await {
                     Ok(a) => a,
                     Err(e) => return AIAgentResponse::Error(e),
                 };
 
+                if let Err(e) = self.check_operator(&agent) {
+                    return AIAgentResponse::Error(e);
+                }
+
                 agent.active = !agent.active;
 
                 match self.state.update_agent(agent).await {
@@ -133,6 +191,88 @@ impl Contract for AIAgentContract {
                     Err(e) => AIAgentResponse::Error(e),
                 }
             }
+
+            AIAgentOperation::SetOperator {
+                agent_id,
+                operator,
+                enabled,
+            } => {
+                let mut agent = match self.state.get_agent(agent_id).await {
+                    Ok(a) => a,
+                    Err(e) => return AIAgentResponse::Error(e),
+                };
+
+                if self.runtime.authenticated_signer() != Some(agent.owner) {
+                    return AIAgentResponse::Error("Only the owner can set operators".to_string());
+                }
+
+                agent.operators.retain(|existing| *existing != operator);
+                if enabled {
+                    agent.operators.push(operator);
+                }
+
+                match self.state.update_agent(agent).await {
+                    Ok(_) => AIAgentResponse::Ok,
+                    Err(e) => AIAgentResponse::Error(e),
+                }
+            }
+
+            AIAgentOperation::Deposit { agent_id, amount } => {
+                match self.deposit(agent_id, amount).await {
+                    Ok(shares) => AIAgentResponse::Shares(shares),
+                    Err(e) => AIAgentResponse::Error(e),
+                }
+            }
+
+            AIAgentOperation::Withdraw { agent_id, shares } => {
+                match self.request_withdrawal(agent_id, shares).await {
+                    Ok(paid) => AIAgentResponse::Payout(paid),
+                    Err(e) => AIAgentResponse::Error(e),
+                }
+            }
+
+            AIAgentOperation::ProcessWithdrawals { agent_id } => {
+                match self.process_withdrawals(agent_id).await {
+                    Ok(paid) => AIAgentResponse::Payout(paid),
+                    Err(e) => AIAgentResponse::Error(e),
+                }
+            }
+
+            AIAgentOperation::Follow {
+                agent_id,
+                leader,
+                ratio_bps,
+                max_bet,
+            } => match self.follow(agent_id, leader, ratio_bps, max_bet).await {
+                Ok(_) => AIAgentResponse::Ok,
+                Err(e) => AIAgentResponse::Error(e),
+            },
+
+            AIAgentOperation::Unfollow { agent_id } => {
+                let agent = match self.state.get_agent(agent_id).await {
+                    Ok(a) => a,
+                    Err(e) => return AIAgentResponse::Error(e),
+                };
+
+                if self.runtime.authenticated_signer() != Some(agent.owner) {
+                    return AIAgentResponse::Error("Only the owner can unfollow".to_string());
+                }
+
+                match self.state.unfollow(agent_id).await {
+                    Ok(true) => AIAgentResponse::Ok,
+                    Ok(false) => {
+                        AIAgentResponse::Error("Agent is not following anyone".to_string())
+                    }
+                    Err(e) => AIAgentResponse::Error(e),
+                }
+            }
+
+            AIAgentOperation::ClaimWinnings { market_id } => {
+                match self.claim_winnings(market_id).await {
+                    Ok(payout) => AIAgentResponse::Payout(payout),
+                    Err(e) => AIAgentResponse::Error(e),
+                }
+            }
         }
     }
 
@@ -140,7 +280,610 @@ impl Contract for AIAgentContract {
         panic!("AI-Agent app does not handle messages");
     }
 
+    async fn process_streams(&mut self, updates: Vec<StreamUpdate>) {
+        let market_app = self.runtime.application_parameters().market_app;
+        let market_source = GenericApplicationId::User(market_app.forget_abi());
+        let market_stream = StreamName::from(MARKET_STREAM_NAME);
+
+        for update in updates {
+            if update.stream_id.application_id != market_source
+                || update.stream_id.stream_name != market_stream
+            {
+                continue;
+            }
+
+            for index in update.new_indices() {
+                let call = MarketOperation::ReadEvent {
+                    chain_id: update.chain_id,
+                    index,
+                };
+                let MarketResponse::Event(MarketEvent::BetPlaced {
+                    market_id,
+                    bettor,
+                    prediction,
+                    amount,
+                    ..
+                }) = self.runtime.call_application(true, market_app, &call)
+                else {
+                    continue;
+                };
+
+                // Agent bets (including copies) are mirrored when they are placed
+                if bettor == self.app_account() {
+                    continue;
+                }
+
+                let leader = CopyLeader::Account(bettor);
+                self.mirror_bet(leader, market_id, prediction, amount, None)
+                    .await
+                    .expect("Failed to mirror bet");
+            }
+        }
+    }
+
     async fn store(mut self) {
         self.state.save().await.expect("Failed to save state");
     }
 }
+
+/// How a market ended
+#[derive(Clone, Copy)]
+enum MarketEnd {
+    Won(Outcome),
+    Cancelled,
+}
+
+/// An open market as listed by the market app's service
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MarketSnapshot {
+    id: u64,
+    /// GraphQL enum name, e.g. `CRYPTO`
+    category: String,
+    up_pool: Amount,
+    down_pool: Amount,
+    closes_at: Timestamp,
+}
+
+impl MarketSnapshot {
+    fn category(&self) -> Result<MarketCategory, String> {
+        [
+            MarketCategory::Crypto,
+            MarketCategory::Sports,
+            MarketCategory::Politics,
+            MarketCategory::Weather,
+            MarketCategory::AI,
+            MarketCategory::Custom,
+        ]
+        .into_iter()
+        .find(|category| category.as_str().eq_ignore_ascii_case(&self.category))
+        .ok_or_else(|| format!("Unknown market category {}", self.category))
+    }
+
+    /// Share of the pool on `prediction`, in basis points; even if the pool is empty
+    fn odds_bps(&self, prediction: Outcome) -> u32 {
+        let total = self.up_pool.saturating_add(self.down_pool);
+        if total.is_zero() {
+            return PRICE_BPS_SCALE / 2;
+        }
+        let side_pool = match prediction {
+            Outcome::Up => self.up_pool,
+            Outcome::Down => self.down_pool,
+        };
+        mul_div(
+            u128::from(side_pool),
+            u128::from(PRICE_BPS_SCALE),
+            u128::from(total),
+        ) as u32
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenMarkets {
+    open_markets: Vec<MarketSnapshot>,
+}
+
+#[derive(Deserialize)]
+struct MarketQuery {
+    market: Option<MarketSnapshot>,
+}
+
+impl AIAgentContract {
+    /// The agent app's own account in the token app, holding all agent balances
+    fn app_account(&mut self) -> AccountOwner {
+        AccountOwner::from(self.runtime.application_id().forget_abi())
+    }
+
+    /// Call the token app on behalf of this app
+    fn call_token(&mut self, operation: TokenOperation) -> Result<(), String> {
+        let token_app = self.runtime.application_parameters().token_app;
+        match self.runtime.call_application(true, token_app, &operation) {
+            TokenResponse::Error(e) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Ask the market app's service for the markets still accepting bets
+    fn open_markets(&mut self) -> Result<Vec<MarketSnapshot>, String> {
+        let market_app = self.runtime.application_parameters().market_app;
+        let query = Request::new("query { openMarkets { id category upPool downPool closesAt } }");
+        let response = self.runtime.query_service(market_app, query);
+
+        let markets: OpenMarkets =
+            async_graphql::from_value(response.data).map_err(|e| e.to_string())?;
+        Ok(markets.open_markets)
+    }
+
+    /// Ask the market app's service for a market's pools and category
+    fn market_snapshot(&mut self, market_id: u64) -> Result<MarketSnapshot, String> {
+        let market_app = self.runtime.application_parameters().market_app;
+        let query = Request::new(format!(
+            "query {{ market(marketId: {}) {{ id category upPool downPool closesAt }} }}",
+            market_id
+        ));
+        let response = self.runtime.query_service(market_app, query);
+
+        let result: MarketQuery =
+            async_graphql::from_value(response.data).map_err(|e| e.to_string())?;
+        result
+            .market
+            .ok_or_else(|| format!("Market {} not found", market_id))
+    }
+
+    /// Stake `amount` of an agent's balance on a market, within its strategy config
+    async fn place_agent_bet(
+        &mut self,
+        mut agent: AIAgent,
+        market: &MarketSnapshot,
+        prediction: Outcome,
+        amount: Amount,
+        win_probability_bps: Option<u32>,
+    ) -> Result<(), String> {
+        if agent.balance < amount {
+            return Err("Insufficient agent balance".to_string());
+        }
+
+        // New bets would keep queued withdrawals waiting indefinitely
+        if !agent.queued_shares.is_zero() {
+            return Err("Agent is waiting to pay out queued withdrawals".to_string());
+        }
+
+        let market_id = market.id;
+        let current_time = self.runtime.system_time();
+        let new_position = !self.state.has_position(agent.id, market_id).await?;
+        agent.config.check(
+            &agent,
+            market.category()?,
+            market.odds_bps(prediction),
+            win_probability_bps,
+            amount,
+            new_position,
+            current_time,
+        )?;
+
+        // The stake is paid from this app's account, through an allowance to the market
+        let market_app_id = self.runtime.application_parameters().market_app;
+        self.call_token(TokenOperation::Approve {
+            spender: AccountOwner::from(market_app_id.forget_abi()),
+            amount,
+            expires_at: None,
+        })?;
+
+        let call = MarketOperation::PlaceBet {
+            market_id,
+            prediction,
+            amount,
+            min_implied_payout: None,
+            max_pool_share: None,
+        };
+        if let MarketResponse::Error(e) = self.runtime.call_application(true, market_app_id, &call)
+        {
+            return Err(e);
+        }
+
+        // Stats are updated when the position settles
+        if self
+            .state
+            .add_stake(agent.id, market_id, prediction, amount)
+            .await?
+        {
+            agent.open_positions += 1;
+        }
+        agent.balance = agent.balance.saturating_sub(amount);
+        agent.open_stake = agent.open_stake.saturating_add(amount);
+        agent.record_spend(amount, current_time);
+
+        self.state.update_agent(agent).await
+    }
+
+    /// Apply an agent's strategy to each open market it has no position in yet,
+    /// in market ID order, up to `AGENT_MAX_BETS_PER_RUN` bets
+    async fn run_agent(&mut self, agent_id: u64) -> Result<u32, String> {
+        if !self.state.get_agent(agent_id).await?.active {
+            return Err("Agent is not active".to_string());
+        }
+
+        let current_time = self.runtime.system_time();
+        let mut placed = 0;
+
+        for market in self.open_markets()? {
+            if placed >= AGENT_MAX_BETS_PER_RUN {
+                break;
+            }
+            if market.closes_at <= current_time
+                || self.state.has_position(agent_id, market.id).await?
+            {
+                continue;
+            }
+
+            let agent = self.state.get_agent(agent_id).await?;
+            let Some((prediction, size)) =
+                agent
+                    .strategy
+                    .plan(market.up_pool, market.down_pool, agent.balance)
+            else {
+                continue;
+            };
+
+            // Strategies carry no win probability, so a Kelly-sized agent sits these out
+            let odds_bps = market.odds_bps(prediction);
+            let amount = agent.config.cap(&agent, size, odds_bps, None, current_time);
+            if u128::from(amount) < MIN_BET_AMOUNT {
+                continue;
+            }
+
+            // A bet the config or the market refuses (e.g. over its limits) is skipped,
+            // and so is each copy a follower refuses. Bets are live once placed, so a
+            // storage failure while copying must reject the block, not return an error.
+            if self
+                .place_agent_bet(agent, &market, prediction, amount, None)
+                .await
+                .is_ok()
+            {
+                placed += 1;
+                let leader = CopyLeader::Agent(agent_id);
+                self.mirror_bet(leader, market.id, prediction, amount, None)
+                    .await
+                    .expect("Failed to mirror bet");
+            }
+        }
+
+        Ok(placed)
+    }
+
+    /// Ask the market app's service how a market ended
+    fn market_end(&mut self, market_id: u64) -> Result<MarketEnd, String> {
+        let market_app = self.runtime.application_parameters().market_app;
+        let query = Request::new(format!(
+            "query {{ market(marketId: {}) {{ status outcome }} }}",
+            market_id
+        ));
+        let response = self.runtime.query_service(market_app, query);
+        let data = response.data.into_json().map_err(|e| e.to_string())?;
+
+        let market = &data["market"];
+        match (market["status"].as_str(), market["outcome"].as_str()) {
+            (Some("RESOLVED"), Some("UP")) => Ok(MarketEnd::Won(Outcome::Up)),
+            (Some("RESOLVED"), Some("DOWN")) => Ok(MarketEnd::Won(Outcome::Down)),
+            (Some("CANCELLED"), _) => Ok(MarketEnd::Cancelled),
+            (Some(_), _) => Err("Market has not been settled yet".to_string()),
+            (None, _) => Err(format!("Market {} not found", market_id)),
+        }
+    }
+
+    /// Claim this app's payout from a settled market and split it among the
+    /// agents by their stake on the winning side (or total stake if cancelled)
+    async fn claim_winnings(&mut self, market_id: u64) -> Result<Amount, String> {
+        let positions: Vec<_> = self
+            .state
+            .market_positions(market_id)
+            .await?
+            .into_iter()
+            .filter(|position| !position.settled)
+            .collect();
+
+        if positions.is_empty() {
+            return Err("No open agent positions in this market".to_string());
+        }
+
+        let end = self.market_end(market_id)?;
+        let winning_stake = |position: &ai_agent::AgentPosition| match end {
+            MarketEnd::Won(Outcome::Up) => position.up_stake,
+            MarketEnd::Won(Outcome::Down) => position.down_stake,
+            MarketEnd::Cancelled => position.total_stake(),
+        };
+
+        let total_winning = positions.iter().fold(Amount::ZERO, |total, p| {
+            total.saturating_add(winning_stake(p))
+        });
+
+        let payout = if total_winning.is_zero() {
+            Amount::ZERO
+        } else {
+            let market_app = self.runtime.application_parameters().market_app;
+            let call = MarketOperation::ClaimWinnings { market_id };
+            match self.runtime.call_application(true, market_app, &call) {
+                MarketResponse::Payout(amount) => amount,
+                MarketResponse::Error(e) => return Err(e),
+                _ => Amount::ZERO,
+            }
+        };
+
+        for mut position in positions {
+            let share = if total_winning.is_zero() {
+                Amount::ZERO
+            } else {
+                Amount::from_attos(mul_div(
+                    u128::from(payout),
+                    u128::from(winning_stake(&position)),
+                    u128::from(total_winning),
+                ))
+            };
+
+            let stake = position.total_stake();
+            let pnl = SignedAmount::difference(share, stake);
+
+            let mut agent = self.state.get_agent(position.agent_id).await?;
+            agent.balance = agent.balance.saturating_add(share);
+            agent.open_positions = agent.open_positions.saturating_sub(1);
+            agent.open_stake = agent.open_stake.saturating_sub(stake);
+
+            // A refunded position is neither a win nor a loss. A position counts as
+            // a win when most of its stake was on the winning outcome.
+            if let MarketEnd::Won(_) = end {
+                let winning = winning_stake(&position);
+                let won = winning > stake.saturating_sub(winning);
+                let previous_peak = agent.peak_profit;
+                agent.update_stats(won, stake, winning, pnl);
+
+                let fee = agent.performance_fee(previous_peak).min(agent.balance);
+                if !fee.is_zero() {
+                    self.call_token(TokenOperation::ApplicationTransfer {
+                        to: agent.owner,
+                        amount: fee,
+                    })?;
+                    agent.balance = agent.balance.saturating_sub(fee);
+                    agent.record_fee(fee);
+                }
+            }
+            let ready = agent.open_positions == 0 && !agent.queued_shares.is_zero();
+            self.state.update_agent(agent).await?;
+            if ready {
+                self.process_withdrawals(position.agent_id).await?;
+            }
+
+            position.settled = true;
+            position.payout = share;
+            position.realized_pnl = pnl;
+            self.state.update_position(position).await?;
+        }
+
+        Ok(payout)
+    }
+
+    /// Move the signer's tokens into an agent's vault and mint shares at the current NAV
+    async fn deposit(&mut self, agent_id: u64, amount: Amount) -> Result<Amount, String> {
+        let depositor = self
+            .runtime
+            .authenticated_signer()
+            .ok_or_else(|| "Must be authenticated".to_string())?;
+        let mut agent = self.state.get_agent(agent_id).await?;
+
+        // Open positions are valued at cost, so the NAV is stale once their markets
+        // resolve: a deposit then could buy into winnings that belong to holders
+        if agent.open_positions > 0 {
+            return Err("Deposits reopen once the agent's positions have settled".to_string());
+        }
+
+        let shares = agent.shares_for(amount);
+        if shares.is_zero() {
+            return Err("Deposit is too small to mint shares".to_string());
+        }
+
+        let to = self.app_account();
+        self.call_token(TokenOperation::Transfer { to, amount })?;
+
+        let held = self.state.get_shares(agent_id, depositor).await?;
+        self.state
+            .set_shares(agent_id, depositor, held.saturating_add(shares))
+            .await?;
+
+        agent.balance = agent.balance.saturating_add(amount);
+        agent.total_shares = agent.total_shares.saturating_add(shares);
+        self.state.update_agent(agent).await?;
+        Ok(shares)
+    }
+
+    /// Queue the signer's shares for redemption, paying out at once if the agent
+    /// has no open positions
+    async fn request_withdrawal(
+        &mut self,
+        agent_id: u64,
+        shares: Amount,
+    ) -> Result<Amount, String> {
+        let owner = self
+            .runtime
+            .authenticated_signer()
+            .ok_or_else(|| "Must be authenticated".to_string())?;
+        let mut agent = self.state.get_agent(agent_id).await?;
+
+        let held = self.state.get_shares(agent_id, owner).await?;
+        if shares.is_zero() || shares > held {
+            return Err(format!("Cannot withdraw {} of {} shares", shares, held));
+        }
+        self.state
+            .set_shares(agent_id, owner, held.saturating_sub(shares))
+            .await?;
+
+        let request = WithdrawalRequest {
+            owner,
+            shares,
+            requested_at: self.runtime.system_time(),
+        };
+        self.state.queue_withdrawal(agent_id, request).await?;
+
+        agent.queued_shares = agent.queued_shares.saturating_add(shares);
+        let ready = agent.open_positions == 0;
+        self.state.update_agent(agent).await?;
+
+        if ready {
+            self.process_withdrawals(agent_id).await
+        } else {
+            Ok(Amount::ZERO)
+        }
+    }
+
+    /// Redeem an agent's queued shares at the current NAV. Only possible once all
+    /// its positions have settled, so the NAV is the free balance.
+    async fn process_withdrawals(&mut self, agent_id: u64) -> Result<Amount, String> {
+        let mut agent = self.state.get_agent(agent_id).await?;
+        if agent.open_positions > 0 {
+            return Err("Withdrawals are paid once the agent's positions settle".to_string());
+        }
+
+        let mut paid = Amount::ZERO;
+        for request in self.state.take_withdrawals(agent_id).await? {
+            let amount = agent.value_of(request.shares);
+            agent.total_shares = agent.total_shares.saturating_sub(request.shares);
+            agent.queued_shares = agent.queued_shares.saturating_sub(request.shares);
+            agent.balance = agent.balance.saturating_sub(amount);
+
+            if !amount.is_zero() {
+                self.call_token(TokenOperation::ApplicationTransfer {
+                    to: request.owner,
+                    amount,
+                })?;
+            }
+            paid = paid.saturating_add(amount);
+        }
+
+        self.state.update_agent(agent).await?;
+        Ok(paid)
+    }
+
+    /// Subscribe an agent to a leader's bets
+    async fn follow(
+        &mut self,
+        agent_id: u64,
+        leader: CopyLeader,
+        ratio_bps: u32,
+        max_bet: Option<Amount>,
+    ) -> Result<(), String> {
+        let agent = self.state.get_agent(agent_id).await?;
+        if self.runtime.authenticated_signer() != Some(agent.owner) {
+            return Err("Only the owner can set the agent's leader".to_string());
+        }
+        if ratio_bps == 0 {
+            return Err("Copy ratio must be positive".to_string());
+        }
+
+        match leader {
+            CopyLeader::Agent(leader_id) if leader_id == agent_id => {
+                return Err("An agent cannot follow itself".to_string());
+            }
+            CopyLeader::Agent(leader_id) => {
+                self.state.get_agent(leader_id).await?;
+            }
+            CopyLeader::Account(_) => self.subscribe_to_market_events(),
+        }
+
+        self.state
+            .follow(CopySubscription {
+                follower_id: agent_id,
+                leader,
+                ratio_bps,
+                max_bet,
+                since: self.runtime.system_time(),
+                bets_copied: 0,
+                amount_copied: Amount::ZERO,
+                bets_skipped: 0,
+            })
+            .await
+    }
+
+    /// Start receiving the market's events, to mirror bets placed by accounts
+    fn subscribe_to_market_events(&mut self) {
+        if *self.state.market_events_subscribed.get() {
+            return;
+        }
+        // Agents act on the market chain, so its stream is the one to follow
+        let market_app = self.runtime.application_parameters().market_app;
+        let chain_id = self.runtime.chain_id();
+        self.runtime.subscribe_to_events(
+            chain_id,
+            market_app.forget_abi(),
+            StreamName::from(MARKET_STREAM_NAME),
+        );
+        self.state.market_events_subscribed.set(true);
+    }
+
+    /// Copy a leader's bet with each agent following it, scaled and capped per
+    /// subscription. Copies are not mirrored again. A copy the market or the
+    /// follower's config refuses counts as skipped; errors are storage failures.
+    async fn mirror_bet(
+        &mut self,
+        leader: CopyLeader,
+        market_id: u64,
+        prediction: Outcome,
+        amount: Amount,
+        win_probability_bps: Option<u32>,
+    ) -> Result<(), String> {
+        let followers = self.state.followers_of(&leader).await?;
+        if followers.is_empty() {
+            return Ok(());
+        }
+
+        // A market that cannot be read skips the bet for every follower
+        let market = self.market_snapshot(market_id).ok();
+        let current_time = self.runtime.system_time();
+
+        for follower_id in followers {
+            let Some(mut subscription) = self.state.get_subscription(follower_id).await? else {
+                continue;
+            };
+            let agent = self.state.get_agent(follower_id).await?;
+
+            let mut size = Amount::from_attos(mul_div(
+                u128::from(amount),
+                u128::from(subscription.ratio_bps),
+                u128::from(PRICE_BPS_SCALE),
+            ));
+            if let Some(max_bet) = subscription.max_bet {
+                size = size.min(max_bet);
+            }
+
+            let copied = match &market {
+                Some(market) if agent.active => {
+                    let odds_bps = market.odds_bps(prediction);
+                    let config = &agent.config;
+                    size = config.cap(&agent, size, odds_bps, win_probability_bps, current_time);
+                    u128::from(size) >= MIN_BET_AMOUNT
+                        && self
+                            .place_agent_bet(agent, market, prediction, size, win_probability_bps)
+                            .await
+                            .is_ok()
+                }
+                _ => false,
+            };
+            if copied {
+                subscription.bets_copied += 1;
+                subscription.amount_copied = subscription.amount_copied.saturating_add(size);
+            } else {
+                subscription.bets_skipped += 1;
+            }
+            self.state.update_subscription(subscription)?;
+        }
+
+        Ok(())
+    }
+
+    /// Require the signer to be the agent's owner or one of its operators
+    fn check_operator(&mut self, agent: &AIAgent) -> Result<(), String> {
+        match self.runtime.authenticated_signer() {
+            Some(signer) if agent.can_operate(&signer) => Ok(()),
+            Some(_) => Err("Not the agent's owner or operator".to_string()),
+            None => Err("Must be authenticated".to_string()),
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use async_graphql::{Request, Response};
+use async_graphql::{ComplexObject, Json, OneofObject, Request, Response, SimpleObject};
 use linera_sdk::{
-    linera_base_types::{Amount, ApplicationId},
+    linera_base_types::{AccountOwner, Amount, ApplicationId, Timestamp},
     abi::{ContractAbi, ServiceAbi},
     graphql::GraphQLMutationRoot,
 };
@@ -25,33 +25,169 @@ pub enum AIAgentOperation {
     CreateAgent {
         name: String,
         strategy: AgentStrategy,
+        /// Owner's cut of vault profits, in basis points
+        performance_fee_bps: u32,
     },
 
-    /// Agent places a bet
+    /// Agent places a bet (owner or operator). `win_probability_bps` is the
+    /// operator's estimate that `prediction` wins, used for Kelly sizing.
     PlaceBet {
         agent_id: u64,
         market_id: u64,
         prediction: Outcome,
         amount: Amount,
+        win_probability_bps: Option<u32>,
     },
 
-    /// Update agent stats after market resolution
-    UpdateStats {
+    /// Toggle agent active status (owner or operator)
+    ToggleAgent {
         agent_id: u64,
-        won: bool,
-        profit: Amount,
     },
 
-    /// Toggle agent active status
-    ToggleAgent {
+    /// Grant or revoke an operator key for an agent (owner only)
+    SetOperator {
+        agent_id: u64,
+        operator: AccountOwner,
+        enabled: bool,
+    },
+
+    /// Set the rules an agent's bets must satisfy (owner only)
+    SetStrategyConfig {
+        agent_id: u64,
+        config: StrategyConfig,
+    },
+
+    /// Let an agent's strategy scan open markets and bet (anyone)
+    RunAgent {
+        agent_id: u64,
+    },
+
+    /// Deposit the signer's tokens into an agent's vault for shares (anyone, while
+    /// the agent has no open positions). The owner funds the agent this way too:
+    /// its whole balance belongs to share holders, so there is no separate top-up.
+    Deposit {
         agent_id: u64,
+        amount: Amount,
+    },
+
+    /// Redeem vault shares. Paid at once if the agent has no open positions,
+    /// otherwise queued until they settle (share holder, the owner included)
+    Withdraw {
+        agent_id: u64,
+        shares: Amount,
     },
+
+    /// Pay out an agent's queued withdrawals once its positions have settled (anyone)
+    ProcessWithdrawals {
+        agent_id: u64,
+    },
+
+    /// Mirror a leader's bets with an agent, scaled by `ratio_bps` of the leader's
+    /// stake and capped at `max_bet`. Replaces any leader it followed (owner only)
+    Follow {
+        agent_id: u64,
+        leader: CopyLeader,
+        ratio_bps: u32,
+        max_bet: Option<Amount>,
+    },
+
+    /// Stop mirroring the agent's leader (owner only)
+    Unfollow {
+        agent_id: u64,
+    },
+
+    /// Settle the agents' positions in a resolved or cancelled market, collecting
+    /// winnings and refunds and updating agent stats (anyone)
+    ClaimWinnings {
+        market_id: u64,
+    },
+}
+
+/// An agent's stake in one market
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct AgentPosition {
+    pub agent_id: u64,
+    pub market_id: u64,
+    pub up_stake: Amount,
+    pub down_stake: Amount,
+    pub settled: bool,
+    /// Winnings or refund credited to the agent at settlement
+    pub payout: Amount,
+    /// Payout minus stake, once settled
+    pub realized_pnl: SignedAmount,
+}
+
+/// Whose bets a copy-trading agent mirrors
+#[derive(Clone, Debug, Deserialize, Serialize, OneofObject, PartialEq, Eq)]
+pub enum CopyLeader {
+    /// Another agent of this app
+    Agent(u64),
+    /// A user betting on the market directly, seen through market events
+    Account(AccountOwner),
+}
+
+/// An agent following a leader, with the results of its copies
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+#[graphql(complex)]
+pub struct CopySubscription {
+    pub follower_id: u64,
+    #[graphql(skip)]
+    pub leader: CopyLeader,
+    /// Share of the leader's stake copied, in basis points
+    pub ratio_bps: u32,
+    /// Largest copied bet
+    pub max_bet: Option<Amount>,
+    pub since: Timestamp,
+    /// Leader bets copied
+    pub bets_copied: u64,
+    /// Stake placed by copies
+    pub amount_copied: Amount,
+    /// Leader bets not copied (inactive agent, too small, over limits, ...)
+    pub bets_skipped: u64,
+}
+
+#[ComplexObject]
+impl CopySubscription {
+    /// The agent or account being followed
+    #[graphql(name = "leader")]
+    async fn leader_json(&self) -> Json<CopyLeader> {
+        Json(self.leader.clone())
+    }
+}
+
+/// Vault shares waiting to be redeemed
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct WithdrawalRequest {
+    pub owner: AccountOwner,
+    pub shares: Amount,
+    pub requested_at: Timestamp,
+}
+
+impl AgentPosition {
+    pub fn new(agent_id: u64, market_id: u64) -> Self {
+        AgentPosition {
+            agent_id,
+            market_id,
+            up_stake: Amount::ZERO,
+            down_stake: Amount::ZERO,
+            settled: false,
+            payout: Amount::ZERO,
+            realized_pnl: SignedAmount::ZERO,
+        }
+    }
+
+    pub fn total_stake(&self) -> Amount {
+        self.up_stake.saturating_add(self.down_stake)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum AIAgentResponse {
     Ok,
     AgentId(u64),
+    Payout(Amount),
+    BetsPlaced(u32),
+    Shares(Amount),
     Error(String),
 }
 
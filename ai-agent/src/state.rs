@@ -1,15 +1,97 @@
-use async_graphql::SimpleObject;
-use linera_sdk::views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext};
-use oracle_ai_abi::AIAgent;
+use async_graphql::{ComplexObject, SimpleObject};
+use linera_sdk::{
+    linera_base_types::{AccountOwner, Amount},
+    views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
+};
+use ai_agent::{AgentPosition, CopyLeader, CopySubscription, WithdrawalRequest};
+use oracle_ai_abi::{AIAgent, Outcome};
 
 #[derive(RootView, SimpleObject)]
 #[view(context = ViewStorageContext)]
+#[graphql(complex)]
 pub struct AIAgentState {
     /// All AI agents
     pub agents: MapView<u64, AIAgent>,
 
     /// Next agent ID
     pub next_agent_id: RegisterView<u64>,
+
+    /// Agent positions per (agent, market)
+    pub positions: MapView<(u64, u64), AgentPosition>,
+
+    /// Agents holding a position, per market
+    #[graphql(skip)]
+    pub market_agents: MapView<u64, Vec<u64>>,
+
+    /// Markets an agent holds a position in, per agent
+    #[graphql(skip)]
+    pub agent_markets: MapView<u64, Vec<u64>>,
+
+    /// Vault shares per (agent, holder), excluding queued ones
+    #[graphql(skip)]
+    pub shares: MapView<(u64, AccountOwner), Amount>,
+
+    /// Queued vault withdrawals per agent, oldest first
+    pub withdrawals: MapView<u64, Vec<WithdrawalRequest>>,
+
+    /// Copy-trading subscription per follower agent
+    pub copy_subscriptions: MapView<u64, CopySubscription>,
+
+    /// Follower agents per leader agent
+    #[graphql(skip)]
+    pub agent_followers: MapView<u64, Vec<u64>>,
+
+    /// Follower agents per leader account
+    #[graphql(skip)]
+    pub account_followers: MapView<AccountOwner, Vec<u64>>,
+
+    /// Whether this app is subscribed to the market event stream
+    #[graphql(skip)]
+    pub market_events_subscribed: RegisterView<bool>,
+}
+
+#[ComplexObject]
+impl AIAgentState {
+    /// Agents ranked by realized profit and loss
+    async fn leaderboard(&self, limit: Option<u32>) -> async_graphql::Result<Vec<AIAgent>> {
+        let mut agents = Vec::new();
+        for id in self.agents.indices().await? {
+            agents.push(self.get_agent(id).await?);
+        }
+
+        agents.sort_by(|a, b| b.total_profit.cmp(&a.total_profit));
+        agents.truncate(limit.unwrap_or(10) as usize);
+        Ok(agents)
+    }
+
+    /// An agent's positions in markets that have not been settled yet
+    async fn open_positions(&self, agent_id: u64) -> async_graphql::Result<Vec<AgentPosition>> {
+        let mut positions = Vec::new();
+        for market_id in self.markets_of_agent(agent_id).await? {
+            if let Some(position) = self.positions.get(&(agent_id, market_id)).await? {
+                if !position.settled {
+                    positions.push(position);
+                }
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Agents copying a leader
+    async fn followers(&self, leader: CopyLeader) -> async_graphql::Result<Vec<CopySubscription>> {
+        let mut subscriptions = Vec::new();
+        for follower_id in self.followers_of(&leader).await? {
+            if let Some(subscription) = self.copy_subscriptions.get(&follower_id).await? {
+                subscriptions.push(subscription);
+            }
+        }
+        Ok(subscriptions)
+    }
+
+    /// Vault shares `owner` holds in an agent, excluding queued ones
+    async fn shares(&self, agent_id: u64, owner: AccountOwner) -> async_graphql::Result<Amount> {
+        Ok(self.get_shares(agent_id, owner).await?)
+    }
 }
 
 impl AIAgentState {
@@ -43,4 +125,228 @@ impl AIAgentState {
             .insert(&agent.id.clone(), agent)
             .map_err(|e| format!("Failed to update agent: {}", e))
     }
+
+    /// Add a stake to an agent's position in a market. Returns whether the position is new.
+    pub async fn add_stake(
+        &mut self,
+        agent_id: u64,
+        market_id: u64,
+        prediction: Outcome,
+        amount: Amount,
+    ) -> Result<bool, String> {
+        let key = (agent_id, market_id);
+        let (mut position, is_new) = match self
+            .positions
+            .get(&key)
+            .await
+            .map_err(|e| format!("Failed to get position: {}", e))?
+        {
+            Some(position) => (position, false),
+            None => {
+                let mut agents = self.agents_in_market(market_id).await?;
+                agents.push(agent_id);
+                self.market_agents
+                    .insert(&market_id, agents)
+                    .map_err(|e| format!("Failed to index position: {}", e))?;
+
+                let mut markets = self.markets_of_agent(agent_id).await?;
+                markets.push(market_id);
+                self.agent_markets
+                    .insert(&agent_id, markets)
+                    .map_err(|e| format!("Failed to index position: {}", e))?;
+
+                (AgentPosition::new(agent_id, market_id), true)
+            }
+        };
+
+        match prediction {
+            Outcome::Up => position.up_stake = position.up_stake.saturating_add(amount),
+            Outcome::Down => position.down_stake = position.down_stake.saturating_add(amount),
+        }
+
+        self.update_position(position).await?;
+        Ok(is_new)
+    }
+
+    /// Update a position
+    pub async fn update_position(&mut self, position: AgentPosition) -> Result<(), String> {
+        self.positions
+            .insert(&(position.agent_id, position.market_id), position)
+            .map_err(|e| format!("Failed to update position: {}", e))
+    }
+
+    /// IDs of agents holding a position in a market
+    pub async fn agents_in_market(&self, market_id: u64) -> Result<Vec<u64>, String> {
+        Ok(self
+            .market_agents
+            .get(&market_id)
+            .await
+            .map_err(|e| format!("Failed to get market agents: {}", e))?
+            .unwrap_or_default())
+    }
+
+    /// Whether an agent has ever staked on a market
+    pub async fn has_position(&self, agent_id: u64, market_id: u64) -> Result<bool, String> {
+        self.positions
+            .contains_key(&(agent_id, market_id))
+            .await
+            .map_err(|e| format!("Failed to get position: {}", e))
+    }
+
+    /// IDs of markets an agent holds a position in
+    pub async fn markets_of_agent(&self, agent_id: u64) -> Result<Vec<u64>, String> {
+        Ok(self
+            .agent_markets
+            .get(&agent_id)
+            .await
+            .map_err(|e| format!("Failed to get agent markets: {}", e))?
+            .unwrap_or_default())
+    }
+
+    /// Vault shares `owner` holds in an agent
+    pub async fn get_shares(&self, agent_id: u64, owner: AccountOwner) -> Result<Amount, String> {
+        Ok(self
+            .shares
+            .get(&(agent_id, owner))
+            .await
+            .map_err(|e| format!("Failed to get shares: {}", e))?
+            .unwrap_or_default())
+    }
+
+    /// Set the vault shares `owner` holds in an agent
+    pub async fn set_shares(
+        &mut self,
+        agent_id: u64,
+        owner: AccountOwner,
+        shares: Amount,
+    ) -> Result<(), String> {
+        if shares.is_zero() {
+            self.shares
+                .remove(&(agent_id, owner))
+                .map_err(|e| format!("Failed to update shares: {}", e))
+        } else {
+            self.shares
+                .insert(&(agent_id, owner), shares)
+                .map_err(|e| format!("Failed to update shares: {}", e))
+        }
+    }
+
+    /// Add a withdrawal to the back of an agent's queue
+    pub async fn queue_withdrawal(
+        &mut self,
+        agent_id: u64,
+        request: WithdrawalRequest,
+    ) -> Result<(), String> {
+        let mut queue = self
+            .withdrawals
+            .get(&agent_id)
+            .await
+            .map_err(|e| format!("Failed to get withdrawals: {}", e))?
+            .unwrap_or_default();
+        queue.push(request);
+        self.withdrawals
+            .insert(&agent_id, queue)
+            .map_err(|e| format!("Failed to queue withdrawal: {}", e))
+    }
+
+    /// Remove and return an agent's queued withdrawals
+    pub async fn take_withdrawals(
+        &mut self,
+        agent_id: u64,
+    ) -> Result<Vec<WithdrawalRequest>, String> {
+        let queue = self
+            .withdrawals
+            .get(&agent_id)
+            .await
+            .map_err(|e| format!("Failed to get withdrawals: {}", e))?
+            .unwrap_or_default();
+        self.withdrawals
+            .remove(&agent_id)
+            .map_err(|e| format!("Failed to clear withdrawals: {}", e))?;
+        Ok(queue)
+    }
+
+    /// IDs of agents copying a leader
+    pub async fn followers_of(&self, leader: &CopyLeader) -> Result<Vec<u64>, String> {
+        let followers = match leader {
+            CopyLeader::Agent(agent_id) => self.agent_followers.get(agent_id).await,
+            CopyLeader::Account(owner) => self.account_followers.get(owner).await,
+        };
+        Ok(followers
+            .map_err(|e| format!("Failed to get followers: {}", e))?
+            .unwrap_or_default())
+    }
+
+    fn set_followers(&mut self, leader: &CopyLeader, followers: Vec<u64>) -> Result<(), String> {
+        let result = match (leader, followers.is_empty()) {
+            (CopyLeader::Agent(id), true) => self.agent_followers.remove(id),
+            (CopyLeader::Agent(id), false) => self.agent_followers.insert(id, followers),
+            (CopyLeader::Account(owner), true) => self.account_followers.remove(owner),
+            (CopyLeader::Account(owner), false) => self.account_followers.insert(owner, followers),
+        };
+        result.map_err(|e| format!("Failed to update followers: {}", e))
+    }
+
+    /// Get the copy-trading subscription of a follower agent, if any
+    pub async fn get_subscription(
+        &self,
+        follower_id: u64,
+    ) -> Result<Option<CopySubscription>, String> {
+        self.copy_subscriptions
+            .get(&follower_id)
+            .await
+            .map_err(|e| format!("Failed to get subscription: {}", e))
+    }
+
+    /// Store a subscription, indexing the follower under its leader
+    pub async fn follow(&mut self, subscription: CopySubscription) -> Result<(), String> {
+        let follower_id = subscription.follower_id;
+        self.unfollow(follower_id).await?;
+
+        let mut followers = self.followers_of(&subscription.leader).await?;
+        followers.push(follower_id);
+        self.set_followers(&subscription.leader, followers)?;
+
+        self.update_subscription(subscription)
+    }
+
+    /// Remove a follower's subscription. Returns whether it had one.
+    pub async fn unfollow(&mut self, follower_id: u64) -> Result<bool, String> {
+        let Some(subscription) = self.get_subscription(follower_id).await? else {
+            return Ok(false);
+        };
+
+        let mut followers = self.followers_of(&subscription.leader).await?;
+        followers.retain(|id| *id != follower_id);
+        self.set_followers(&subscription.leader, followers)?;
+
+        self.copy_subscriptions
+            .remove(&follower_id)
+            .map_err(|e| format!("Failed to remove subscription: {}", e))?;
+        Ok(true)
+    }
+
+    /// Update a subscription's stats
+    pub fn update_subscription(&mut self, subscription: CopySubscription) -> Result<(), String> {
+        let follower_id = subscription.follower_id;
+        self.copy_subscriptions
+            .insert(&follower_id, subscription)
+            .map_err(|e| format!("Failed to update subscription: {}", e))
+    }
+
+    /// All agent positions in a market
+    pub async fn market_positions(&self, market_id: u64) -> Result<Vec<AgentPosition>, String> {
+        let mut positions = Vec::new();
+        for agent_id in self.agents_in_market(market_id).await? {
+            if let Some(position) = self
+                .positions
+                .get(&(agent_id, market_id))
+                .await
+                .map_err(|e| format!("Failed to get position: {}", e))?
+            {
+                positions.push(position);
+            }
+        }
+        Ok(positions)
+    }
 }
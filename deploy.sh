@@ -70,6 +70,7 @@ linera project publish-and-create oracle \
 
 echo ""
 read -p "Enter Oracle app ID: " ORACLE_APP
+echo "Submit SetOracle with this ID to the market app so it can resolve markets."
 
 # AI-Agent app
 echo ""
@@ -80,6 +81,16 @@ linera project publish-and-create ai-agent \
 echo ""
 read -p "Enter AI-Agent app ID: " AGENT_APP
 
+# Governance app
+echo ""
+echo "Deploying Governance app..."
+linera project publish-and-create governance \
+  --json-parameters "{\"token_app\": \"$TOKEN_APP\", \"market_app\": \"$MARKET_APP\", \"oracle_app\": \"$ORACLE_APP\", \"voting_period_micros\": 259200000000, \"quorum\": \"1000\", \"proposal_threshold\": \"100\"}"
+
+echo ""
+read -p "Enter Governance app ID: " GOVERNANCE_APP
+echo "Submit SetGovernance with this ID to the token, market and oracle apps to hand them over."
+
 # Save app IDs
 echo ""
 echo "💾 Saving app IDs to .env..."
@@ -89,6 +100,7 @@ TOKEN_APP=$TOKEN_APP
 MARKET_APP=$MARKET_APP
 ORACLE_APP=$ORACLE_APP
 AGENT_APP=$AGENT_APP
+GOVERNANCE_APP=$GOVERNANCE_APP
 
 # GraphQL Endpoint
 VITE_GRAPHQL_ENDPOINT=http://localhost:8080/graphql
@@ -0,0 +1,454 @@
+#![cfg_attr(target_arch = "wasm32", no_main)]
+
+mod state;
+
+use self::state::GovernanceState;
+use governance::{
+    GovernanceOperation, GovernanceParameters, GovernanceResponse, Proposal, ProposalAction,
+    ProposalStatus, VoteRecord,
+};
+use linera_sdk::{
+    linera_base_types::{AccountOwner, Amount, TimeDelta, Timestamp},
+    abi::WithContractAbi,
+    views::{RootView, View},
+    Contract, ContractRuntime,
+};
+use market::{MarketOperation, MarketResponse};
+use oracle::{OracleOperation, OracleResponse};
+use token::{TokenOperation, TokenResponse};
+
+pub struct GovernanceContract {
+    state: GovernanceState,
+    runtime: ContractRuntime<Self>,
+}
+
+linera_sdk::contract!(GovernanceContract);
+
+impl WithContractAbi for GovernanceContract {
+    type Abi = governance::GovernanceAbi;
+}
+
+impl Contract for GovernanceContract {
+    type Message = ();
+    type Parameters = GovernanceParameters;
+    type InstantiationArgument = ();
+    type EventValue = ();
+
+    async fn load(runtime: ContractRuntime<Self>) -> Self {
+        let state = GovernanceState::load(runtime.root_view_storage_context())
+            .await
+            .expect("Failed to load state");
+        GovernanceContract { state, runtime }
+    }
+
+    async fn instantiate(&mut self, _argument: Self::InstantiationArgument) {
+        // No initialization needed
+    }
+
+    async fn execute_operation(&mut self, operation: Self::Operation) -> Self::Response {
+        match operation {
+            GovernanceOperation::Propose {
+                description,
+                action,
+            } => {
+                let proposer = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return GovernanceResponse::Error("Must be authenticated".to_string()),
+                };
+
+                let weight = match self.voting_weight(proposer, None) {
+                    Ok(weight) => weight,
+                    Err(e) => return GovernanceResponse::Error(e),
+                };
+
+                let params = self.runtime.application_parameters();
+                if weight.is_zero() || weight < params.proposal_threshold {
+                    return GovernanceResponse::Error(format!(
+                        "Proposing requires at least {} staked",
+                        params.proposal_threshold
+                    ));
+                }
+
+                let created_at = self.runtime.system_time();
+                let proposal = Proposal {
+                    id: 0, // Will be set by create_proposal
+                    proposer,
+                    description,
+                    action,
+                    created_at,
+                    voting_ends_at: created_at
+                        .saturating_add(TimeDelta::from_micros(params.voting_period_micros)),
+                    votes_for: Amount::ZERO,
+                    votes_against: Amount::ZERO,
+                    status: ProposalStatus::Active,
+                    execution_error: None,
+                };
+
+                match self.state.create_proposal(proposal).await {
+                    Ok(id) => GovernanceResponse::ProposalId(id),
+                    Err(e) => GovernanceResponse::Error(e),
+                }
+            }
+
+            GovernanceOperation::Vote {
+                proposal_id,
+                approve,
+            } => {
+                let voter = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return GovernanceResponse::Error("Must be authenticated".to_string()),
+                };
+
+                let proposal = match self.state.get_proposal(proposal_id).await {
+                    Ok(p) => p,
+                    Err(e) => return GovernanceResponse::Error(e),
+                };
+
+                if proposal.status != ProposalStatus::Active
+                    || self.runtime.system_time() >= proposal.voting_ends_at
+                {
+                    return GovernanceResponse::Error("Voting has ended".to_string());
+                }
+
+                // Weight is the stake held before the proposal was created, so tokens
+                // cannot be unstaked, moved and restaked to vote again
+                let weight = match self.voting_weight(voter, Some(proposal.created_at)) {
+                    Ok(weight) => weight,
+                    Err(e) => return GovernanceResponse::Error(e),
+                };
+                if weight.is_zero() {
+                    return GovernanceResponse::Error("Only stakers can vote".to_string());
+                }
+
+                let vote = VoteRecord { approve, weight };
+                match self.state.cast_vote(proposal_id, voter, vote).await {
+                    Ok(_) => GovernanceResponse::Ok,
+                    Err(e) => GovernanceResponse::Error(e),
+                }
+            }
+
+            GovernanceOperation::Execute { proposal_id } => {
+                let mut proposal = match self.state.get_proposal(proposal_id).await {
+                    Ok(p) => p,
+                    Err(e) => return GovernanceResponse::Error(e),
+                };
+
+                if proposal.status != ProposalStatus::Active {
+                    return GovernanceResponse::Error("Proposal already settled".to_string());
+                }
+                if self.runtime.system_time() < proposal.voting_ends_at {
+                    return GovernanceResponse::Error("Voting is still open".to_string());
+                }
+
+                let quorum = self.runtime.application_parameters().quorum;
+                proposal.status = if !proposal.passed(quorum) {
+                    ProposalStatus::Rejected
+                } else {
+                    match self.apply(proposal.action.clone()) {
+                        Ok(_) => ProposalStatus::Executed,
+                        Err(e) => {
+                            proposal.execution_error = Some(e);
+                            ProposalStatus::Failed
+                        }
+                    }
+                };
+
+                match self.state.update_proposal(proposal).await {
+                    Ok(_) => GovernanceResponse::Ok,
+                    Err(e) => GovernanceResponse::Error(e),
+                }
+            }
+        }
+    }
+
+    async fn execute_message(&mut self, _message: Self::Message) {
+        panic!("Governance app does not handle messages");
+    }
+
+    async fn store(mut self) {
+        self.state.save().await.expect("Failed to save state");
+    }
+}
+
+impl GovernanceContract {
+    /// Staked balance of `owner` in the token app, now or just before `at`
+    fn voting_weight(
+        &mut self,
+        owner: AccountOwner,
+        at: Option<Timestamp>,
+    ) -> Result<Amount, String> {
+        let token_app = self.runtime.application_parameters().token_app;
+        let call = TokenOperation::VotingWeight { owner, at };
+
+        match self.runtime.call_application(true, token_app, &call) {
+            TokenResponse::Balance(weight) => Ok(weight),
+            TokenResponse::Error(e) => Err(e),
+            TokenResponse::Ok => Err("Unexpected response from the token app".to_string()),
+        }
+    }
+
+    /// Run a passed proposal through the target app's admin operation
+    fn apply(&mut self, action: ProposalAction) -> Result<(), String> {
+        let params = self.runtime.application_parameters();
+
+        match action {
+            ProposalAction::MarketConfig(config) => {
+                let call = MarketOperation::UpdateConfig { config };
+                match self.runtime.call_application(true, params.market_app, &call) {
+                    MarketResponse::Error(e) => Err(e),
+                    _ => Ok(()),
+                }
+            }
+            ProposalAction::OracleConfig(config) => {
+                let call = OracleOperation::UpdateConfig { config };
+                match self.runtime.call_application(true, params.oracle_app, &call) {
+                    OracleResponse::Error(e) => Err(e),
+                    _ => Ok(()),
+                }
+            }
+            ProposalAction::BonusGuards(guards) => {
+                let call = TokenOperation::SetBonusGuards { guards };
+                match self.runtime.call_application(true, params.token_app, &call) {
+                    TokenResponse::Error(e) => Err(e),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use futures::FutureExt as _;
+    use governance::{
+        GovernanceOperation, GovernanceParameters, GovernanceResponse, ProposalAction,
+        ProposalStatus,
+    };
+    use linera_sdk::{
+        linera_base_types::{AccountOwner, Amount, ApplicationId, ChainId, CryptoHash, Timestamp},
+        util::BlockingWait,
+        views::View,
+        Contract, ContractRuntime,
+    };
+    use market::{MarketConfig, MarketOperation, MarketResponse};
+    use token::{TokenOperation, TokenResponse};
+
+    use super::{GovernanceContract, GovernanceState};
+
+    const ALICE: AccountOwner = AccountOwner::Address20([2; 20]);
+    const BOB: AccountOwner = AccountOwner::Address20([3; 20]);
+    const CAROL: AccountOwner = AccountOwner::Address20([4; 20]);
+    const DAVE: AccountOwner = AccountOwner::Address20([5; 20]);
+
+    const VOTING_PERIOD: u64 = 1_000;
+
+    /// Market operations governance has run
+    type MarketCalls = Rc<RefCell<Vec<MarketOperation>>>;
+
+    #[test]
+    fn proposing_requires_the_threshold_stake() {
+        let (mut governance, _) = create_and_instantiate_governance();
+        assert_error(execute(&mut governance, BOB, propose()));
+        assert_error(execute(&mut governance, DAVE, propose()));
+        let proposal_id = assert_proposal_id(execute(&mut governance, ALICE, propose()));
+        assert_eq!(proposal(&governance, proposal_id).proposer, ALICE);
+    }
+
+    #[test]
+    fn proposal_that_passes_is_applied_to_the_target_app() {
+        let (mut governance, market_calls) = create_and_instantiate_governance();
+        let proposal_id = assert_proposal_id(execute(&mut governance, ALICE, propose()));
+
+        assert_ok(execute(&mut governance, ALICE, vote(proposal_id, true)));
+        assert_error(execute(&mut governance, ALICE, vote(proposal_id, false)));
+        assert_ok(execute(&mut governance, BOB, vote(proposal_id, false)));
+
+        let execute_proposal = GovernanceOperation::Execute { proposal_id };
+        assert_error(execute(&mut governance, BOB, execute_proposal.clone()));
+
+        governance
+            .runtime
+            .set_system_time(Timestamp::from(10 + VOTING_PERIOD));
+        assert_error(execute(&mut governance, BOB, vote(proposal_id, false)));
+        assert_ok(execute(&mut governance, BOB, execute_proposal.clone()));
+        assert_error(execute(&mut governance, BOB, execute_proposal));
+
+        let proposal = proposal(&governance, proposal_id);
+        assert_eq!(proposal.votes_for, Amount::from_tokens(1_200));
+        assert_eq!(proposal.votes_against, Amount::from_tokens(50));
+        assert_eq!(proposal.status, ProposalStatus::Executed);
+        assert!(matches!(
+            market_calls.borrow().as_slice(),
+            [MarketOperation::UpdateConfig { .. }]
+        ));
+    }
+
+    #[test]
+    fn proposal_short_of_quorum_is_rejected() {
+        let (mut governance, market_calls) = create_and_instantiate_governance();
+        let proposal_id = assert_proposal_id(execute(&mut governance, ALICE, propose()));
+        assert_ok(execute(&mut governance, BOB, vote(proposal_id, true)));
+
+        governance
+            .runtime
+            .set_system_time(Timestamp::from(10 + VOTING_PERIOD));
+        let execute_proposal = GovernanceOperation::Execute { proposal_id };
+        assert_ok(execute(&mut governance, BOB, execute_proposal));
+
+        assert_eq!(
+            proposal(&governance, proposal_id).status,
+            ProposalStatus::Rejected
+        );
+        assert!(market_calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn votes_count_only_stake_held_before_the_proposal() {
+        let (mut governance, _) = create_and_instantiate_governance();
+        let proposal_id = assert_proposal_id(execute(&mut governance, ALICE, propose()));
+
+        // Carol's stake arrives after the proposal, so it carries no weight on it
+        governance.runtime.set_system_time(Timestamp::from(30));
+        assert_error(execute(&mut governance, CAROL, vote(proposal_id, true)));
+        assert!(governance
+            .state
+            .get_vote(proposal_id, CAROL)
+            .now_or_never()
+            .unwrap()
+            .is_none());
+
+        // A later proposal counts it
+        let later = assert_proposal_id(execute(&mut governance, ALICE, propose()));
+        assert_ok(execute(&mut governance, CAROL, vote(later, true)));
+        assert_eq!(
+            proposal(&governance, later).votes_for,
+            Amount::from_tokens(500)
+        );
+    }
+
+    /// Voting weight the mock token app reports: each account stakes once, at a fixed time
+    fn weight_at(owner: AccountOwner, at: Option<Timestamp>) -> Amount {
+        let (staked_at, staked) = match owner {
+            ALICE => (0, Amount::from_tokens(1_200)),
+            BOB => (0, Amount::from_tokens(50)),
+            CAROL => (20, Amount::from_tokens(500)),
+            _ => return Amount::ZERO,
+        };
+        if at.is_none_or(|at| Timestamp::from(staked_at) < at) {
+            staked
+        } else {
+            Amount::ZERO
+        }
+    }
+
+    fn create_and_instantiate_governance() -> (GovernanceContract, MarketCalls) {
+        let token_app = ApplicationId::new(CryptoHash::from([4; 4]));
+        let market_app = ApplicationId::new(CryptoHash::from([5; 4]));
+        let market_calls = MarketCalls::default();
+        let recorded = market_calls.clone();
+
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(GovernanceParameters {
+                token_app: token_app.with_abi(),
+                market_app: market_app.with_abi(),
+                oracle_app: ApplicationId::new(CryptoHash::from([6; 4])).with_abi(),
+                voting_period_micros: VOTING_PERIOD,
+                quorum: Amount::from_tokens(1_000),
+                proposal_threshold: Amount::from_tokens(100),
+            })
+            .with_application_id(ApplicationId::new(CryptoHash::from([7; 4])))
+            .with_chain_id(ChainId(CryptoHash::from([8; 4])))
+            .with_authenticated_caller_id(None)
+            .with_system_time(Timestamp::from(10))
+            .with_call_application_handler(move |_authenticated, application, operation| {
+                if application == token_app {
+                    let weight = match bcs::from_bytes(&operation).unwrap() {
+                        TokenOperation::VotingWeight { owner, at } => weight_at(owner, at),
+                        operation => panic!("Unexpected token call: {:?}", operation),
+                    };
+                    bcs::to_bytes(&TokenResponse::Balance(weight)).unwrap()
+                } else {
+                    assert_eq!(application, market_app);
+                    recorded
+                        .borrow_mut()
+                        .push(bcs::from_bytes(&operation).unwrap());
+                    bcs::to_bytes(&MarketResponse::Ok).unwrap()
+                }
+            });
+        let mut contract = GovernanceContract {
+            state: GovernanceState::load(runtime.root_view_storage_context())
+                .blocking_wait()
+                .expect("Failed to read from mock key value store"),
+            runtime,
+        };
+
+        contract
+            .instantiate(())
+            .now_or_never()
+            .expect("Initialization of governance state should not await anything");
+        (contract, market_calls)
+    }
+
+    fn execute(
+        contract: &mut GovernanceContract,
+        signer: AccountOwner,
+        operation: GovernanceOperation,
+    ) -> GovernanceResponse {
+        contract.runtime.set_authenticated_signer(Some(signer));
+        contract
+            .execute_operation(operation)
+            .now_or_never()
+            .expect("Execution of governance operation should not await anything")
+    }
+
+    fn propose() -> GovernanceOperation {
+        GovernanceOperation::Propose {
+            description: "Lower the platform fee".to_string(),
+            action: ProposalAction::MarketConfig(MarketConfig {
+                platform_fee_percent: 3,
+                ..MarketConfig::default()
+            }),
+        }
+    }
+
+    fn vote(proposal_id: u64, approve: bool) -> GovernanceOperation {
+        GovernanceOperation::Vote {
+            proposal_id,
+            approve,
+        }
+    }
+
+    fn proposal(contract: &GovernanceContract, proposal_id: u64) -> governance::Proposal {
+        contract
+            .state
+            .get_proposal(proposal_id)
+            .now_or_never()
+            .unwrap()
+            .unwrap()
+    }
+
+    fn assert_proposal_id(response: GovernanceResponse) -> u64 {
+        match response {
+            GovernanceResponse::ProposalId(id) => id,
+            response => panic!("Unexpected response: {:?}", response),
+        }
+    }
+
+    fn assert_ok(response: GovernanceResponse) {
+        assert!(
+            matches!(response, GovernanceResponse::Ok),
+            "Unexpected response: {:?}",
+            response
+        );
+    }
+
+    fn assert_error(response: GovernanceResponse) {
+        assert!(
+            matches!(response, GovernanceResponse::Error(_)),
+            "Unexpected response: {:?}",
+            response
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use async_graphql::{ComplexObject, Enum, Json, OneofObject, Request, Response, SimpleObject};
+use linera_sdk::{
+    linera_base_types::{AccountOwner, Amount, ApplicationId, Timestamp},
+    abi::{ContractAbi, ServiceAbi},
+    graphql::GraphQLMutationRoot,
+};
+use market::MarketConfig;
+use oracle::OracleConfig;
+use serde::{Deserialize, Serialize};
+use token::BonusGuards;
+
+pub struct GovernanceAbi;
+
+impl ContractAbi for GovernanceAbi {
+    type Operation = GovernanceOperation;
+    type Response = GovernanceResponse;
+}
+
+impl ServiceAbi for GovernanceAbi {
+    type Query = Request;
+    type QueryResponse = Response;
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, GraphQLMutationRoot)]
+pub enum GovernanceOperation {
+    /// Propose a protocol parameter change (stakers only)
+    Propose {
+        description: String,
+        action: ProposalAction,
+    },
+    /// Vote on an open proposal with the signer's staked balance before it was created
+    Vote { proposal_id: u64, approve: bool },
+    /// Apply a proposal that passed, or close it as rejected (anyone, once voting has ended)
+    Execute { proposal_id: u64 },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum GovernanceResponse {
+    Ok,
+    ProposalId(u64),
+    Error(String),
+}
+
+/// A parameter change, applied through the target app's admin operation
+#[derive(Clone, Debug, Deserialize, Serialize, OneofObject)]
+pub enum ProposalAction {
+    /// Replace the market app's fee, default limits and duration bounds
+    MarketConfig(MarketConfig),
+    /// Replace the oracle app's resolution quorum
+    OracleConfig(OracleConfig),
+    /// Replace the token app's daily bonus guards
+    BonusGuards(BonusGuards),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
+pub enum ProposalStatus {
+    Active,
+    /// Voting ended without enough support
+    Rejected,
+    Executed,
+    /// The vote passed but the target app refused the change
+    Failed,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+#[graphql(complex)]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: AccountOwner,
+    pub description: String,
+    #[graphql(skip)]
+    pub action: ProposalAction,
+    pub created_at: Timestamp,
+    pub voting_ends_at: Timestamp,
+    pub votes_for: Amount,
+    pub votes_against: Amount,
+    pub status: ProposalStatus,
+    /// Error returned by the target app if execution failed
+    pub execution_error: Option<String>,
+}
+
+impl Proposal {
+    /// Whether the vote reached `quorum` with more weight for than against
+    pub fn passed(&self, quorum: Amount) -> bool {
+        self.votes_for.saturating_add(self.votes_against) >= quorum
+            && self.votes_for > self.votes_against
+    }
+}
+
+#[ComplexObject]
+impl Proposal {
+    /// The proposed change
+    #[graphql(name = "action")]
+    async fn action_json(&self) -> Json<ProposalAction> {
+        Json(self.action.clone())
+    }
+}
+
+/// A recorded vote
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct VoteRecord {
+    pub approve: bool,
+    pub weight: Amount,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GovernanceParameters {
+    pub token_app: ApplicationId<token::TokenAbi>,
+    pub market_app: ApplicationId<market::MarketAbi>,
+    pub oracle_app: ApplicationId<oracle::OracleAbi>,
+    /// How long proposals stay open
+    pub voting_period_micros: u64,
+    /// Smallest total vote weight for a proposal to pass
+    pub quorum: Amount,
+    /// Staked balance needed to submit a proposal
+    pub proposal_threshold: Amount,
+}
+
+impl Default for GovernanceResponse {
+    fn default() -> Self {
+        GovernanceResponse::Ok
+    }
+}
@@ -0,0 +1,73 @@
+#![cfg_attr(target_arch = "wasm32", no_main)]
+
+mod state;
+
+use std::sync::Arc;
+
+use async_graphql::{EmptySubscription, Object, Request, Response, Schema};
+use linera_sdk::{
+    abi::WithServiceAbi,
+    views::View,
+    Service, ServiceRuntime,
+};
+use self::state::GovernanceState;
+
+pub struct GovernanceService {
+    state: Arc<GovernanceState>,
+}
+
+linera_sdk::service!(GovernanceService);
+
+impl WithServiceAbi for GovernanceService {
+    type Abi = governance::GovernanceAbi;
+}
+
+impl Service for GovernanceService {
+    type Parameters = governance::GovernanceParameters;
+
+    async fn new(runtime: ServiceRuntime<Self>) -> Self {
+        let state = GovernanceState::load(runtime.root_view_storage_context())
+            .await
+            .expect("Failed to load state");
+        GovernanceService {
+            state: Arc::new(state),
+        }
+    }
+
+    async fn handle_query(&self, request: Request) -> Response {
+        let schema = Schema::build(
+            self.state.clone(),
+            MutationRoot,
+            EmptySubscription,
+        )
+        .finish();
+
+        schema.execute(request).await
+    }
+}
+
+struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    async fn propose(&self, description: String, action: governance::ProposalAction) -> Vec<u8> {
+        let op = governance::GovernanceOperation::Propose {
+            description,
+            action,
+        };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn vote(&self, proposal_id: u64, approve: bool) -> Vec<u8> {
+        let op = governance::GovernanceOperation::Vote {
+            proposal_id,
+            approve,
+        };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn execute(&self, proposal_id: u64) -> Vec<u8> {
+        let op = governance::GovernanceOperation::Execute { proposal_id };
+        bcs::to_bytes(&op).unwrap()
+    }
+}
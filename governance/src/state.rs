@@ -0,0 +1,98 @@
+use async_graphql::{ComplexObject, SimpleObject};
+use governance::{Proposal, VoteRecord};
+use linera_sdk::{
+    linera_base_types::AccountOwner,
+    views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
+};
+
+#[derive(RootView, SimpleObject)]
+#[view(context = ViewStorageContext)]
+#[graphql(complex)]
+pub struct GovernanceState {
+    /// All proposals
+    pub proposals: MapView<u64, Proposal>,
+
+    /// Votes per (proposal, voter)
+    #[graphql(skip)]
+    pub votes: MapView<(u64, AccountOwner), VoteRecord>,
+
+    /// Next proposal ID
+    pub next_proposal_id: RegisterView<u64>,
+}
+
+#[ComplexObject]
+impl GovernanceState {
+    /// How an account voted on a proposal
+    async fn vote(&self, proposal_id: u64, voter: AccountOwner) -> Option<VoteRecord> {
+        self.get_vote(proposal_id, voter).await
+    }
+}
+
+impl GovernanceState {
+    /// Store a new proposal
+    pub async fn create_proposal(&mut self, proposal: Proposal) -> Result<u64, String> {
+        let id = *self.next_proposal_id.get();
+
+        let mut proposal_with_id = proposal;
+        proposal_with_id.id = id;
+
+        self.proposals
+            .insert(&id, proposal_with_id)
+            .map_err(|e| format!("Failed to create proposal: {}", e))?;
+
+        self.next_proposal_id.set(id + 1);
+        Ok(id)
+    }
+
+    /// Get a proposal by ID
+    pub async fn get_proposal(&self, id: u64) -> Result<Proposal, String> {
+        self.proposals
+            .get(&id)
+            .await
+            .map_err(|e| format!("Failed to get proposal: {}", e))?
+            .ok_or_else(|| format!("Proposal {} not found", id))
+    }
+
+    /// Update a proposal
+    pub async fn update_proposal(&mut self, proposal: Proposal) -> Result<(), String> {
+        let id = proposal.id;
+        self.proposals
+            .insert(&id, proposal)
+            .map_err(|e| format!("Failed to update proposal: {}", e))
+    }
+
+    /// Record a vote and add its weight to the tally
+    pub async fn cast_vote(
+        &mut self,
+        proposal_id: u64,
+        voter: AccountOwner,
+        vote: VoteRecord,
+    ) -> Result<(), String> {
+        let key = (proposal_id, voter);
+        let already_voted = self
+            .votes
+            .contains_key(&key)
+            .await
+            .map_err(|e| e.to_string())?;
+        if already_voted {
+            return Err("Already voted on this proposal".to_string());
+        }
+
+        let mut proposal = self.get_proposal(proposal_id).await?;
+        if vote.approve {
+            proposal.votes_for = proposal.votes_for.saturating_add(vote.weight);
+        } else {
+            proposal.votes_against = proposal.votes_against.saturating_add(vote.weight);
+        }
+
+        self.votes
+            .insert(&key, vote)
+            .map_err(|e| format!("Failed to record vote: {}", e))?;
+        self.update_proposal(proposal).await
+    }
+
+    /// A voter's vote on a proposal, if any
+    pub async fn get_vote(&self, proposal_id: u64, voter: AccountOwner) -> Option<VoteRecord> {
+        self.votes.get(&(proposal_id, voter)).await.unwrap_or(None)
+    }
+}
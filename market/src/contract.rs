@@ -4,13 +4,17 @@ mod state;
 
 use self::state::MarketState;
 use linera_sdk::{
-    linera_base_types::{AccountOwner, Amount, TimeDelta},
+    linera_base_types::{Account, AccountOwner, Amount, ChainId, StreamName, TimeDelta},
     abi::WithContractAbi,
     views::{RootView, View},
     Contract, ContractRuntime,
 };
-use market::{MarketOperation, MarketParameters, MarketResponse};
+use market::{
+    MarketEvent, MarketMessage, MarketOperation, MarketParameters, MarketResponse, ReceiptKind,
+    RemoteReceipt, MARKET_STREAM_NAME,
+};
 use oracle_ai_abi::*;
+use token::{TokenOperation, TokenResponse};
 
 pub struct MarketContract {
     state: MarketState,
@@ -24,10 +28,10 @@ impl WithContractAbi for MarketContract {
 }
 
 impl Contract for MarketContract {
-    type Message = ();
+    type Message = MarketMessage;
     type Parameters = MarketParameters;
     type InstantiationArgument = ();
-    type EventValue = ();
+    type EventValue = MarketEvent;
 
     async fn load(runtime: ContractRuntime<Self>) -> Self {
         let state = MarketState::load(runtime.root_view_storage_context())
@@ -48,18 +52,24 @@ impl Contract for MarketContract {
                 category,
                 duration_minutes,
                 oracle_mode,
+                limits,
             } => {
                 let creator = match self.runtime.authenticated_signer() {
                     Some(signer) => signer,
                     None => return MarketResponse::Error("Must be authenticated".to_string()),
                 };
 
+                let config = self.state.config.get().clone();
+                let caps = config
+                    .default_limits
+                    .unwrap_or_else(|| self.runtime.application_parameters().default_limits);
+
                 // Validate duration
                 let duration_micros = duration_minutes * 60 * 1_000_000;
-                if duration_micros < MIN_MARKET_DURATION_MICROS {
+                if duration_micros < config.min_duration_micros {
                     return MarketResponse::Error("Duration too short".to_string());
                 }
-                if duration_micros > MAX_MARKET_DURATION_MICROS {
+                if duration_micros > config.max_duration_micros {
                     return MarketResponse::Error("Duration too long".to_string());
                 }
 
@@ -82,10 +92,23 @@ impl Contract for MarketContract {
                     down_pool: Amount::ZERO,
                     oracle_mode,
                     resolution_source: None,
+                    limits: limits.unwrap_or_default().within(&caps),
+                    fee_percent: config.platform_fee_percent,
                 };
 
+                let (question, category) = (market.question.clone(), market.category);
+
                 match self.state.create_market(market).await {
-                    Ok(id) => MarketResponse::MarketId(id),
+                    Ok(id) => {
+                        self.emit(MarketEvent::MarketCreated {
+                            market_id: id,
+                            creator,
+                            question,
+                            category,
+                            closes_at,
+                        });
+                        MarketResponse::MarketId(id)
+                    }
                     Err(e) => MarketResponse::Error(e),
                 }
             }
@@ -94,119 +117,554 @@ impl Contract for MarketContract {
                 market_id,
                 prediction,
                 amount,
+                min_implied_payout,
+                max_pool_share,
+            } => {
+                let bettor = match self.acting_account() {
+                    Some(account) => account,
+                    None => return MarketResponse::Error("Must be authenticated".to_string()),
+                };
+
+                // Markets live on the chain that created the application
+                let market_chain = self.runtime.application_creator_chain_id();
+                if self.runtime.chain_id() != market_chain {
+                    if self.runtime.authenticated_caller_id().is_some() {
+                        return MarketResponse::Error(
+                            "Applications must bet on the market chain".to_string(),
+                        );
+                    }
+                    // The stake travels with the bet, in the same message bundle: if the
+                    // market chain rejects the bundle, the token credit bounces back too
+                    let escrow = Account {
+                        chain_id: market_chain,
+                        owner: self.escrow_account(),
+                    };
+                    let token_app = self.runtime.application_parameters().token_app;
+                    let transfer = TokenOperation::TransferToAccount {
+                        to: escrow,
+                        amount,
+                    };
+                    if let TokenResponse::Error(e) =
+                        self.runtime.call_application(true, token_app, &transfer)
+                    {
+                        return MarketResponse::Error(e);
+                    }
+                    let message = MarketMessage::PlaceBet {
+                        market_id,
+                        prediction,
+                        amount,
+                        min_implied_payout,
+                        max_pool_share,
+                    };
+                    self.runtime
+                        .prepare_message(message)
+                        .with_authentication()
+                        .with_tracking()
+                        .send_to(market_chain);
+                    return MarketResponse::Ok;
+                }
+
+                match self
+                    .place_bet(
+                        bettor,
+                        market_id,
+                        prediction,
+                        amount,
+                        min_implied_payout,
+                        max_pool_share,
+                    )
+                    .await
+                {
+                    Ok(_) => MarketResponse::Ok,
+                    Err(e) => MarketResponse::Error(e),
+                }
+            }
+
+            MarketOperation::SellPosition {
+                market_id,
+                side,
+                amount,
+                min_price_bps,
             } => {
-                let bettor = match self.runtime.authenticated_signer() {
+                let seller = match self.runtime.authenticated_signer() {
                     Some(signer) => signer,
                     None => return MarketResponse::Error("Must be authenticated".to_string()),
                 };
 
-                // Validate amount
-                if u128::from(amount) < MIN_BET_AMOUNT {
+                if amount.is_zero() {
+                    return MarketResponse::Error("Nothing to sell".to_string());
+                }
+
+                let market = match self.state.get_market(market_id).await {
+                    Ok(m) => m,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+
+                let current_time = self.runtime.system_time();
+                if !market.can_bet(current_time) {
+                    return MarketResponse::Error("Market is closed for trading".to_string());
+                }
+
+                let held = match self.state.side_stake_of(market_id, &seller, side).await {
+                    Ok(held) => held,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+                if held < amount {
+                    return MarketResponse::Error(format!(
+                        "Insufficient position: {} < {}",
+                        held, amount
+                    ));
+                }
+
+                // The stake is sold to other traders rather than taken out of the pools,
+                // so an exit is priced by buyers and leaves the winners' payouts alone
+                let mut order = LimitOrder {
+                    id: 0,
+                    market_id,
+                    owner: seller,
+                    outcome: side,
+                    side: OrderSide::Sell,
+                    price_bps: min_price_bps,
+                    quantity: amount,
+                    filled: Amount::ZERO,
+                    escrow: Amount::ZERO,
+                    status: OrderStatus::Open,
+                    placed_at: current_time,
+                };
+                let bids = match self.state.get_open_orders(market_id).await {
+                    Ok(orders) => orders,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+                let available = bids
+                    .iter()
+                    .filter(|bid| bid.can_fill(&order))
+                    .fold(Amount::ZERO, |acc, bid| acc.saturating_add(bid.remaining()));
+                if available.is_zero() {
                     return MarketResponse::Error(format!(
-                        "Bet too small, minimum is {}",
+                        "No bids at or above {} bps",
+                        min_price_bps
+                    ));
+                }
+                order.quantity = amount.min(available);
+
+                if let Err(e) = self
+                    .state
+                    .reduce_stake(market_id, &seller, side, order.quantity)
+                    .await
+                {
+                    return MarketResponse::Error(e);
+                }
+
+                let (order, proceeds) = match self.match_order(order).await {
+                    Ok(matched) => matched,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+
+                let record = PositionRecord {
+                    market_id,
+                    owner: seller,
+                    side,
+                    stake: order.filled,
+                    proceeds,
+                    realized_pnl: SignedAmount::difference(proceeds, order.filled),
+                    closed_at: current_time,
+                };
+
+                match self.state.record_position(record).await {
+                    Ok(_) => MarketResponse::Payout(proceeds),
+                    Err(e) => MarketResponse::Error(e),
+                }
+            }
+
+            MarketOperation::PlaceLimitOrder {
+                market_id,
+                outcome,
+                side,
+                price_bps,
+                quantity,
+            } => {
+                let owner = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return MarketResponse::Error("Must be authenticated".to_string()),
+                };
+
+                if u128::from(quantity) < MIN_BET_AMOUNT {
+                    return MarketResponse::Error(format!(
+                        "Order too small, minimum is {}",
                         MIN_BET_AMOUNT
                     ));
                 }
+                if price_bps == 0 || price_bps > MAX_ORDER_PRICE_BPS {
+                    return MarketResponse::Error(format!(
+                        "Price must be between 1 and {} bps",
+                        MAX_ORDER_PRICE_BPS
+                    ));
+                }
 
-                // Get and validate market
-                let mut market = match self.state.get_market(market_id).await {
+                let market = match self.state.get_market(market_id).await {
                     Ok(m) => m,
                     Err(e) => return MarketResponse::Error(e),
                 };
 
                 let current_time = self.runtime.system_time();
                 if !market.can_bet(current_time) {
-                    return MarketResponse::Error("Market is closed for betting".to_string());
+                    return MarketResponse::Error("Market is closed for trading".to_string());
                 }
 
-                // Update market pools
-                market.total_pool = market.total_pool.saturating_add(amount);
-                match prediction {
-                    Outcome::Up => market.up_pool = market.up_pool.saturating_add(amount),
-                    Outcome::Down => market.down_pool = market.down_pool.saturating_add(amount),
+                // Buying stake counts towards the per-user limit; the pool does not grow
+                if side == OrderSide::Buy {
+                    if let Some(max_user_stake) = market.limits.max_user_stake {
+                        let held = match self.state.stake_of(market_id, &owner).await {
+                            Ok(h) => h,
+                            Err(e) => return MarketResponse::Error(e),
+                        };
+                        let total = held.saturating_add(quantity);
+                        if total > max_user_stake {
+                            return MarketResponse::Error(format!(
+                                "Total stake of {} exceeds the per-user limit of {}",
+                                total, max_user_stake
+                            ));
+                        }
+                    }
                 }
 
-                // Save updated market
-                if let Err(e) = self.state.update_market(market).await {
-                    return MarketResponse::Error(e);
-                }
+                // Escrow collateral: tokens for a buy, stake for a sell
+                let escrow = match side {
+                    OrderSide::Buy => {
+                        let cost = order_cost(quantity, price_bps);
+                        if let Err(e) = self.collect(owner, cost) {
+                            return MarketResponse::Error(e);
+                        }
+                        cost
+                    }
+                    OrderSide::Sell => {
+                        if let Err(e) = self
+                            .state
+                            .reduce_stake(market_id, &owner, outcome, quantity)
+                            .await
+                        {
+                            return MarketResponse::Error(e);
+                        }
+                        Amount::ZERO
+                    }
+                };
 
-                // Record bet
-                let bet = Bet {
+                let order = LimitOrder {
+                    id: 0, // Will be set by create_order
                     market_id,
-                    bettor,
-                    prediction,
-                    amount,
+                    owner,
+                    outcome,
+                    side,
+                    price_bps,
+                    quantity,
+                    filled: Amount::ZERO,
+                    escrow,
+                    status: OrderStatus::Open,
                     placed_at: current_time,
-                    claimed: false,
                 };
 
-                match self.state.place_bet(bet).await {
+                let mut order = match self.match_order(order).await {
+                    Ok((o, _)) => o,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+
+                order.id = match self.state.create_order(order.clone()).await {
+                    Ok(id) => id,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+
+                // Fully filled orders are kept for history but leave the book
+                if !order.is_open() {
+                    if let Err(e) = self.state.update_order(order.clone()).await {
+                        return MarketResponse::Error(e);
+                    }
+                }
+
+                MarketResponse::OrderId(order.id)
+            }
+
+            MarketOperation::CancelOrder { order_id } => {
+                let canceller = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return MarketResponse::Error("Must be authenticated".to_string()),
+                };
+
+                let mut order = match self.state.get_order(order_id).await {
+                    Ok(o) => o,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+
+                if order.owner != canceller {
+                    return MarketResponse::Error("Only the order owner can cancel".to_string());
+                }
+                if !order.is_open() {
+                    return MarketResponse::Error("Order is not open".to_string());
+                }
+
+                if let Err(e) = self.release_escrow(&mut order).await {
+                    return MarketResponse::Error(e);
+                }
+                order.status = OrderStatus::Cancelled;
+
+                match self.state.update_order(order).await {
                     Ok(_) => MarketResponse::Ok,
                     Err(e) => MarketResponse::Error(e),
                 }
             }
 
-            MarketOperation::ResolveMarket { market_id, outcome } => {
-                let resolver = match self.runtime.authenticated_signer() {
+            MarketOperation::ProvideLiquidity { market_id, amount } => {
+                let provider = match self.runtime.authenticated_signer() {
                     Some(signer) => signer,
                     None => return MarketResponse::Error("Must be authenticated".to_string()),
                 };
 
+                if u128::from(amount) < MIN_BET_AMOUNT {
+                    return MarketResponse::Error(format!(
+                        "Deposit too small, minimum is {}",
+                        MIN_BET_AMOUNT
+                    ));
+                }
+
                 let mut market = match self.state.get_market(market_id).await {
                     Ok(m) => m,
                     Err(e) => return MarketResponse::Error(e),
                 };
 
-                if market.status != MarketStatus::Active {
-                    return MarketResponse::Error("Market already resolved or cancelled".to_string());
+                if !market.can_bet(self.runtime.system_time()) {
+                    return MarketResponse::Error("Market is closed for liquidity".to_string());
                 }
 
-                let current_time = self.runtime.system_time();
+                if let Some(max_pool) = market.limits.max_pool {
+                    let pool_after = market.total_pool.saturating_add(amount);
+                    if pool_after > max_pool {
+                        return MarketResponse::Error(format!(
+                            "Pool of {} would exceed the market limit of {}",
+                            pool_after, max_pool
+                        ));
+                    }
+                }
 
-                market.status = MarketStatus::Resolved;
-                market.outcome = Some(outcome);
-                market.resolved_at = Some(current_time);
+                let mut pool = match self.state.get_liquidity(market_id).await {
+                    Ok(p) => p,
+                    Err(e) => return MarketResponse::Error(e),
+                };
 
-                // Calculate platform fee (5%)
-                let fee = Amount::from_attos(u128::from(market.total_pool) * 5 / 100);
+                if let Err(e) = self.collect(provider, amount) {
+                    return MarketResponse::Error(e);
+                }
 
-                let current_fees = *self.state.platform_fees.get();
-                let new_fees = current_fees.saturating_add(fee);
-                self.state.platform_fees.set(new_fees);
+                // Seed both pools at the current odds so the price does not move
+                let up_part = if market.total_pool.is_zero() {
+                    Amount::from_attos(u128::from(amount) / 2)
+                } else {
+                    Amount::from_attos(mul_div(
+                        u128::from(amount),
+                        u128::from(market.up_pool),
+                        u128::from(market.total_pool),
+                    ))
+                };
+                let down_part = amount.saturating_sub(up_part);
 
-                match self.state.update_market(market).await {
-                    Ok(_) => MarketResponse::Ok,
+                let minted = pool.shares_for(amount, pool.net_asset_value(&market));
+
+                market.total_pool = market.total_pool.saturating_add(amount);
+                market.up_pool = market.up_pool.saturating_add(up_part);
+                market.down_pool = market.down_pool.saturating_add(down_part);
+                pool.up_seed = pool.up_seed.saturating_add(up_part);
+                pool.down_seed = pool.down_seed.saturating_add(down_part);
+                pool.total_shares = pool.total_shares.saturating_add(minted);
+
+                let shares = self
+                    .state
+                    .lp_shares_of(market_id, &provider)
+                    .await
+                    .saturating_add(minted);
+
+                if let Err(e) = self.state.update_market(market).await {
+                    return MarketResponse::Error(e);
+                }
+                if let Err(e) = self.state.update_liquidity(pool).await {
+                    return MarketResponse::Error(e);
+                }
+
+                match self.state.set_lp_shares(market_id, provider, shares).await {
+                    Ok(_) => MarketResponse::Shares(minted),
                     Err(e) => MarketResponse::Error(e),
                 }
             }
 
-            MarketOperation::ClaimWinnings { market_id } => {
-                let bettor = match self.runtime.authenticated_signer() {
+            MarketOperation::WithdrawLiquidity { market_id, shares } => {
+                let provider = match self.runtime.authenticated_signer() {
                     Some(signer) => signer,
                     None => return MarketResponse::Error("Must be authenticated".to_string()),
                 };
 
-                // Check if already claimed
-                if self.state.has_claimed(market_id, &bettor).await {
-                    return MarketResponse::Error("Already claimed".to_string());
+                let held = self.state.lp_shares_of(market_id, &provider).await;
+                if shares.is_zero() || held < shares {
+                    return MarketResponse::Error(format!(
+                        "Insufficient LP shares: {} < {}",
+                        held, shares
+                    ));
                 }
 
-                let payout = match self.state.calculate_payout(market_id, &bettor).await {
+                let mut market = match self.state.get_market(market_id).await {
+                    Ok(m) => m,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+                let mut pool = match self.state.get_liquidity(market_id).await {
                     Ok(p) => p,
                     Err(e) => return MarketResponse::Error(e),
                 };
 
-                if payout.is_zero() {
-                    return MarketResponse::Error("No winnings to claim".to_string());
+                let current_time = self.runtime.system_time();
+                let up_out = pool.portion(pool.up_seed, shares);
+                let down_out = pool.portion(pool.down_seed, shares);
+                let fees_out = pool.portion(pool.fees, shares);
+
+                let value = match market.status {
+                    // Before close, the seed is simply taken back out of the pools
+                    MarketStatus::Active if market.can_bet(current_time) => {
+                        let seed_out = up_out.saturating_add(down_out);
+                        market.total_pool = market.total_pool.saturating_sub(seed_out);
+                        market.up_pool = market.up_pool.saturating_sub(up_out);
+                        market.down_pool = market.down_pool.saturating_sub(down_out);
+                        seed_out.saturating_add(fees_out)
+                    }
+                    MarketStatus::Active | MarketStatus::Closed => {
+                        return MarketResponse::Error(
+                            "Liquidity is locked until the market resolves".to_string(),
+                        );
+                    }
+                    MarketStatus::Resolved | MarketStatus::Cancelled => {
+                        pool.portion(pool.net_asset_value(&market), shares)
+                    }
+                };
+
+                pool.up_seed = pool.up_seed.saturating_sub(up_out);
+                pool.down_seed = pool.down_seed.saturating_sub(down_out);
+                pool.fees = pool.fees.saturating_sub(fees_out);
+                pool.total_shares = pool.total_shares.saturating_sub(shares);
+
+                if let Err(e) = self.pay(provider, value) {
+                    return MarketResponse::Error(e);
+                }
+                if let Err(e) = self.state.update_market(market).await {
+                    return MarketResponse::Error(e);
+                }
+                if let Err(e) = self.state.update_liquidity(pool).await {
+                    return MarketResponse::Error(e);
+                }
+                if let Err(e) = self
+                    .state
+                    .set_lp_shares(market_id, provider, held.saturating_sub(shares))
+                    .await
+                {
+                    return MarketResponse::Error(e);
+                }
+
+                MarketResponse::Payout(value)
+            }
+
+            MarketOperation::ResolveMarket { market_id, outcome } => {
+                let mut market = match self.state.get_market(market_id).await {
+                    Ok(m) => m,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+
+                if !self.can_resolve(&market) {
+                    return MarketResponse::Error(
+                        "Only the oracle app, or the creator of a manual market, can resolve it"
+                            .to_string(),
+                    );
+                }
+
+                if !matches!(market.status, MarketStatus::Active | MarketStatus::Closed) {
+                    return MarketResponse::Error("Market already resolved or cancelled".to_string());
+                }
+
+                if let Err(e) = self.cancel_open_orders(market_id).await {
+                    return MarketResponse::Error(e);
+                }
+
+                let current_time = self.runtime.system_time();
+
+                market.status = MarketStatus::Resolved;
+                market.outcome = Some(outcome);
+                market.resolved_at = Some(current_time);
+
+                // Calculate platform fee
+                let fee = Amount::from_attos(
+                    u128::from(market.total_pool) * u128::from(market.fee_percent) / 100,
+                );
+
+                if let Err(e) = self.accrue_fee(market_id, fee).await {
+                    return MarketResponse::Error(e);
+                }
+
+                match self.state.update_market(market).await {
+                    Ok(_) => {
+                        self.emit(MarketEvent::MarketResolved {
+                            market_id,
+                            outcome,
+                            resolved_at: current_time,
+                        });
+                        MarketResponse::Ok
+                    }
+                    Err(e) => MarketResponse::Error(e),
+                }
+            }
+
+            MarketOperation::CloseMarket { market_id } => {
+                let mut market = match self.state.get_market(market_id).await {
+                    Ok(m) => m,
+                    Err(e) => return MarketResponse::Error(e),
+                };
+
+                if !market.is_closed(self.runtime.system_time()) {
+                    return MarketResponse::Error("Market is not due to close".to_string());
                 }
 
-                // Mark as claimed
-                if let Err(e) = self.state.mark_claimed(market_id, bettor).await {
+                if let Err(e) = self.cancel_open_orders(market_id).await {
                     return MarketResponse::Error(e);
                 }
 
-                MarketResponse::Payout(payout)
+                market.status = MarketStatus::Closed;
+
+                match self.state.update_market(market).await {
+                    Ok(_) => {
+                        self.emit(MarketEvent::MarketClosed { market_id });
+                        MarketResponse::Ok
+                    }
+                    Err(e) => MarketResponse::Error(e),
+                }
+            }
+
+            MarketOperation::ClaimWinnings { market_id } => {
+                if self.runtime.authenticated_signer().is_none() {
+                    return MarketResponse::Error("Must be authenticated".to_string());
+                }
+
+                let market_chain = self.runtime.application_creator_chain_id();
+                if self.runtime.chain_id() != market_chain {
+                    if self.runtime.authenticated_caller_id().is_some() {
+                        return MarketResponse::Error(
+                            "Applications must claim on the market chain".to_string(),
+                        );
+                    }
+                    self.runtime
+                        .prepare_message(MarketMessage::ClaimWinnings { market_id })
+                        .with_authentication()
+                        .with_tracking()
+                        .send_to(market_chain);
+                    return MarketResponse::Ok;
+                }
+
+                let bettor = self.acting_account().unwrap();
+                let chain_id = self.runtime.chain_id();
+                match self.claim_winnings(bettor, market_id, chain_id).await {
+                    Ok(payout) => MarketResponse::Payout(payout),
+                    Err(e) => MarketResponse::Error(e),
+                }
             }
 
             MarketOperation::CancelMarket { market_id } => {
@@ -225,25 +683,1025 @@ impl Contract for MarketContract {
                     return MarketResponse::Error("Only creator can cancel".to_string());
                 }
 
-                if market.status != MarketStatus::Active {
+                if !matches!(market.status, MarketStatus::Active | MarketStatus::Closed) {
                     return MarketResponse::Error("Cannot cancel resolved market".to_string());
                 }
 
+                if let Err(e) = self.cancel_open_orders(market_id).await {
+                    return MarketResponse::Error(e);
+                }
+
                 market.status = MarketStatus::Cancelled;
 
                 match self.state.update_market(market).await {
-                    Ok(_) => MarketResponse::Ok,
+                    Ok(_) => {
+                        self.emit(MarketEvent::MarketCancelled { market_id });
+                        MarketResponse::Ok
+                    }
                     Err(e) => MarketResponse::Error(e),
                 }
             }
+
+            MarketOperation::DistributeFees => {
+                if !self.is_admin() {
+                    return MarketResponse::Error("Only admin can distribute fees".to_string());
+                }
+
+                let fees = *self.state.platform_fees.get();
+                if fees.is_zero() {
+                    return MarketResponse::Error("No fees to distribute".to_string());
+                }
+
+                // Fees are held in escrow; the token app debits this app's account
+                let token_app = self.runtime.application_parameters().token_app;
+                let call = TokenOperation::DistributeRewards { amount: fees };
+                if let TokenResponse::Error(e) =
+                    self.runtime.call_application(true, token_app, &call)
+                {
+                    return MarketResponse::Error(e);
+                }
+
+                self.state.platform_fees.set(Amount::ZERO);
+                MarketResponse::Payout(fees)
+            }
+
+            MarketOperation::UpdateConfig { config } => {
+                if !self.is_admin() {
+                    return MarketResponse::Error("Only admin can update the config".to_string());
+                }
+                if let Err(e) = config.validate() {
+                    return MarketResponse::Error(e);
+                }
+
+                self.state.config.set(config);
+                MarketResponse::Ok
+            }
+
+            MarketOperation::SetGovernance { governance_app } => {
+                if !self.is_admin() {
+                    return MarketResponse::Error("Only admin can set governance".to_string());
+                }
+
+                self.state.governance_app.set(Some(governance_app));
+                MarketResponse::Ok
+            }
+
+            MarketOperation::SetOracle { oracle_app } => {
+                if !self.is_admin() {
+                    return MarketResponse::Error("Only admin can set the oracle".to_string());
+                }
+
+                self.state.oracle_app.set(Some(oracle_app));
+                MarketResponse::Ok
+            }
+
+            MarketOperation::ReadEvent { chain_id, index } => {
+                if self.runtime.authenticated_caller_id().is_none() {
+                    return MarketResponse::Error(
+                        "Only applications can read market events".to_string(),
+                    );
+                }
+                let name = StreamName::from(MARKET_STREAM_NAME);
+                MarketResponse::Event(self.runtime.read_event(chain_id, name, index))
+            }
         }
     }
 
-    async fn execute_message(&mut self, _message: Self::Message) {
-        panic!("Market app does not handle messages");
+    async fn execute_message(&mut self, message: Self::Message) {
+        let is_bouncing = self.runtime.message_is_bouncing().unwrap_or_default();
+        let origin = self
+            .runtime
+            .message_origin_chain_id()
+            .expect("Incoming message must have an origin chain");
+        let timestamp = self.runtime.system_time();
+
+        match message {
+            MarketMessage::PlaceBet {
+                market_id,
+                prediction,
+                amount,
+                min_implied_payout,
+                max_pool_share,
+            } => {
+                let bettor = self
+                    .runtime
+                    .authenticated_signer()
+                    .expect("Remote bets must be authenticated");
+
+                // The market chain rejected the bundle; the stake's token credit
+                // bounced with it and the token app has refunded the bettor
+                if is_bouncing {
+                    self.state
+                        .record_receipt(RemoteReceipt {
+                            market_id,
+                            owner: bettor,
+                            kind: ReceiptKind::BetRefused,
+                            amount,
+                            reason: Some("Rejected by the market chain".to_string()),
+                            timestamp,
+                        })
+                        .await
+                        .expect("Failed to record receipt");
+                    return;
+                }
+
+                // The stake has already been credited to escrow, ahead of this message
+                let checked = self
+                    .check_bet(
+                        bettor,
+                        market_id,
+                        prediction,
+                        amount,
+                        min_implied_payout,
+                        max_pool_share,
+                    )
+                    .await;
+                let result = match checked {
+                    Ok(market) => self.record_bet(market, bettor, prediction, amount).await,
+                    Err(e) => Err(e),
+                };
+
+                let (kind, reason) = match result {
+                    Ok(_) => (ReceiptKind::BetAccepted, None),
+                    Err(e) => {
+                        // Send the stake back to the bettor's chain
+                        let refund_to = Account {
+                            chain_id: origin,
+                            owner: bettor,
+                        };
+                        self.pay_to_account(refund_to, amount)
+                            .expect("Failed to refund refused bet");
+                        (ReceiptKind::BetRefused, Some(e))
+                    }
+                };
+
+                self.send_receipt(
+                    origin,
+                    RemoteReceipt {
+                        market_id,
+                        owner: bettor,
+                        kind,
+                        amount,
+                        reason,
+                        timestamp,
+                    },
+                );
+            }
+
+            MarketMessage::ClaimWinnings { market_id } => {
+                let bettor = self
+                    .runtime
+                    .authenticated_signer()
+                    .expect("Remote claims must be authenticated");
+
+                if is_bouncing {
+                    return;
+                }
+
+                let claimed = self.claim_winnings(bettor, market_id, origin).await;
+                let (kind, amount, reason) = match claimed {
+                    Ok(payout) => (ReceiptKind::Payout, payout, None),
+                    Err(e) => (ReceiptKind::ClaimRefused, Amount::ZERO, Some(e)),
+                };
+
+                self.send_receipt(
+                    origin,
+                    RemoteReceipt {
+                        market_id,
+                        owner: bettor,
+                        kind,
+                        amount,
+                        reason,
+                        timestamp,
+                    },
+                );
+            }
+
+            MarketMessage::Receipt(receipt) => {
+                // Bets are counted on the market chain; mirror accepted ones here so the
+                // token app on this chain sees them in its bonus guard
+                if receipt.kind == ReceiptKind::BetAccepted {
+                    self.state
+                        .count_bet(&receipt.owner)
+                        .await
+                        .expect("Failed to count bet");
+                }
+                self.state
+                    .record_receipt(receipt)
+                    .await
+                    .expect("Failed to record receipt");
+            }
+        }
     }
 
     async fn store(mut self) {
         self.state.save().await.expect("Failed to save state");
     }
-}
\ No newline at end of file
+}
+
+impl MarketContract {
+    /// Whether the caller is the governance app or the signer is a chain owner
+    fn is_admin(&mut self) -> bool {
+        let governance_app = *self.state.governance_app.get();
+        if governance_app.is_some() && self.runtime.authenticated_caller_id() == governance_app {
+            return true;
+        }
+        match self.runtime.authenticated_signer() {
+            Some(signer) => self.runtime.chain_ownership().super_owners.contains(&signer),
+            None => false,
+        }
+    }
+
+    /// Whether the caller may resolve `market`: the oracle app, or for a manual
+    /// market its creator signing directly
+    fn can_resolve(&mut self, market: &Market) -> bool {
+        let oracle_app = *self.state.oracle_app.get();
+        let caller = self.runtime.authenticated_caller_id();
+        if oracle_app.is_some() && caller == oracle_app {
+            return true;
+        }
+        market.oracle_mode == OracleMode::Manual
+            && caller.is_none()
+            && self.runtime.authenticated_signer() == Some(market.creator)
+    }
+
+    /// Publish an event on the market stream
+    fn emit(&mut self, event: MarketEvent) {
+        self.runtime.emit(StreamName::from(MARKET_STREAM_NAME), &event);
+    }
+
+    /// The market's own account in the token app, holding escrowed tokens
+    fn escrow_account(&mut self) -> AccountOwner {
+        AccountOwner::from(self.runtime.application_id().forget_abi())
+    }
+
+    /// The calling application's account if called by one, otherwise the signer
+    fn acting_account(&mut self) -> Option<AccountOwner> {
+        match self.runtime.authenticated_caller_id() {
+            Some(app) => Some(AccountOwner::from(app)),
+            None => self.runtime.authenticated_signer(),
+        }
+    }
+
+    /// Move tokens from `from` into escrow. A calling application pays through
+    /// an allowance it has granted this app; anyone else pays as the signer.
+    fn collect(&mut self, from: AccountOwner, amount: Amount) -> Result<(), String> {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        let token_app = self.runtime.application_parameters().token_app;
+        let to = self.escrow_account();
+        let caller = self.runtime.authenticated_caller_id().map(AccountOwner::from);
+        let call = if caller == Some(from) {
+            TokenOperation::TransferFrom {
+                owner: from,
+                to,
+                amount,
+            }
+        } else {
+            TokenOperation::Transfer { to, amount }
+        };
+
+        match self.runtime.call_application(true, token_app, &call) {
+            TokenResponse::Error(e) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Pay tokens out of escrow
+    fn pay(&mut self, to: AccountOwner, amount: Amount) -> Result<(), String> {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        let token_app = self.runtime.application_parameters().token_app;
+        let call = TokenOperation::ApplicationTransfer { to, amount };
+
+        match self.runtime.call_application(true, token_app, &call) {
+            TokenResponse::Error(e) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Pay tokens out of escrow to an account on any chain
+    fn pay_to_account(&mut self, to: Account, amount: Amount) -> Result<(), String> {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        let token_app = self.runtime.application_parameters().token_app;
+        let call = TokenOperation::ApplicationTransferToAccount { to, amount };
+
+        match self.runtime.call_application(true, token_app, &call) {
+            TokenResponse::Error(e) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validate and record a bet, collecting the stake into escrow
+    async fn place_bet(
+        &mut self,
+        bettor: AccountOwner,
+        market_id: u64,
+        prediction: Outcome,
+        amount: Amount,
+        min_implied_payout: Option<Amount>,
+        max_pool_share: Option<u32>,
+    ) -> Result<(), String> {
+        let market = self
+            .check_bet(
+                bettor,
+                market_id,
+                prediction,
+                amount,
+                min_implied_payout,
+                max_pool_share,
+            )
+            .await?;
+
+        // Stake is held in escrow until payout
+        self.collect(bettor, amount)?;
+
+        self.record_bet(market, bettor, prediction, amount).await
+    }
+
+    /// Check a bet against the market's status, limits and the bettor's slippage
+    /// guards, returning the market to record it in
+    async fn check_bet(
+        &mut self,
+        bettor: AccountOwner,
+        market_id: u64,
+        prediction: Outcome,
+        amount: Amount,
+        min_implied_payout: Option<Amount>,
+        max_pool_share: Option<u32>,
+    ) -> Result<Market, String> {
+        // Validate amount
+        if u128::from(amount) < MIN_BET_AMOUNT {
+            return Err(format!("Bet too small, minimum is {}", MIN_BET_AMOUNT));
+        }
+
+        // Get and validate market
+        let market = self.state.get_market(market_id).await?;
+
+        let current_time = self.runtime.system_time();
+        if !market.can_bet(current_time) {
+            return Err("Market is closed for betting".to_string());
+        }
+
+        // Exposure limits
+        let held = self.state.stake_of(market_id, &bettor).await?;
+        market.limits.check(&market, amount, Some(held))?;
+
+        // Slippage protection against pools moving since the quote
+        if let Some(min_payout) = min_implied_payout {
+            let payout = market.implied_payout_after(prediction, amount);
+            if payout < min_payout {
+                return Err(format!(
+                    "Slippage exceeded: implied payout {} below minimum {}",
+                    payout, min_payout
+                ));
+            }
+        }
+        if let Some(max_share) = max_pool_share {
+            let share = market.pool_share_after_bps(prediction, amount);
+            if share > max_share {
+                return Err(format!(
+                    "Slippage exceeded: pool share {} bps above maximum {} bps",
+                    share, max_share
+                ));
+            }
+        }
+
+        Ok(market)
+    }
+
+    /// Add an escrowed stake to the market's pools and record the bet
+    async fn record_bet(
+        &mut self,
+        mut market: Market,
+        bettor: AccountOwner,
+        prediction: Outcome,
+        amount: Amount,
+    ) -> Result<(), String> {
+        let market_id = market.id;
+        let current_time = self.runtime.system_time();
+
+        // Update market pools
+        market.total_pool = market.total_pool.saturating_add(amount);
+        match prediction {
+            Outcome::Up => market.up_pool = market.up_pool.saturating_add(amount),
+            Outcome::Down => market.down_pool = market.down_pool.saturating_add(amount),
+        }
+
+        // Save updated market
+        self.state.update_market(market).await?;
+
+        // Record bet
+        let bet = Bet {
+            market_id,
+            bettor,
+            prediction,
+            amount,
+            placed_at: current_time,
+            claimed: false,
+        };
+        self.state.place_bet(bet).await?;
+        self.state.count_bet(&bettor).await?;
+
+        self.emit(MarketEvent::BetPlaced {
+            market_id,
+            bettor,
+            prediction,
+            amount,
+            placed_at: current_time,
+        });
+        Ok(())
+    }
+
+    /// Pay out a bettor's winnings (or refund, for a cancelled market) to their
+    /// account on `chain_id`
+    async fn claim_winnings(
+        &mut self,
+        bettor: AccountOwner,
+        market_id: u64,
+        chain_id: ChainId,
+    ) -> Result<Amount, String> {
+        // Check if already claimed
+        if self.state.has_claimed(market_id, &bettor).await {
+            return Err("Already claimed".to_string());
+        }
+
+        let payout = self.state.calculate_payout(market_id, &bettor).await?;
+
+        if payout.is_zero() {
+            return Err("No winnings to claim".to_string());
+        }
+
+        let to = Account {
+            chain_id,
+            owner: bettor,
+        };
+        self.pay_to_account(to, payout)?;
+
+        // Mark as claimed
+        self.state.mark_claimed(market_id, bettor).await?;
+
+        self.emit(MarketEvent::WinningsClaimed {
+            market_id,
+            winner: bettor,
+            amount: payout,
+        });
+        Ok(payout)
+    }
+
+    /// Send the outcome of a remote request back to the chain it came from
+    fn send_receipt(&mut self, target: ChainId, receipt: RemoteReceipt) {
+        self.runtime
+            .prepare_message(MarketMessage::Receipt(receipt))
+            .send_to(target);
+    }
+
+    /// Split a fee between the market's LPs and the platform
+    async fn accrue_fee(&mut self, market_id: u64, fee: Amount) -> Result<(), String> {
+        let mut pool = self.state.get_liquidity(market_id).await?;
+
+        let lp_fee = if pool.total_shares.is_zero() {
+            Amount::ZERO
+        } else {
+            Amount::from_attos(u128::from(fee) * u128::from(LP_FEE_SHARE_PERCENT) / 100)
+        };
+
+        if !lp_fee.is_zero() {
+            pool.fees = pool.fees.saturating_add(lp_fee);
+            self.state.update_liquidity(pool).await?;
+        }
+
+        let current_fees = *self.state.platform_fees.get();
+        self.state
+            .platform_fees
+            .set(current_fees.saturating_add(fee.saturating_sub(lp_fee)));
+        Ok(())
+    }
+
+    /// Return an order's unfilled collateral to its owner
+    async fn release_escrow(&mut self, order: &mut LimitOrder) -> Result<(), String> {
+        match order.side {
+            OrderSide::Buy => {
+                self.pay(order.owner, order.escrow)?;
+                order.escrow = Amount::ZERO;
+            }
+            OrderSide::Sell => {
+                let bet = Bet {
+                    market_id: order.market_id,
+                    bettor: order.owner,
+                    prediction: order.outcome,
+                    amount: order.remaining(),
+                    placed_at: self.runtime.system_time(),
+                    claimed: false,
+                };
+                if !bet.amount.is_zero() {
+                    self.state.place_bet(bet).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Cancel every open order on a market, returning its escrowed tokens and stake.
+    /// Run whenever a market stops trading, so no stake is left outside `bets`
+    /// when winnings are claimed.
+    async fn cancel_open_orders(&mut self, market_id: u64) -> Result<(), String> {
+        for mut order in self.state.get_open_orders(market_id).await? {
+            self.release_escrow(&mut order).await?;
+            order.status = OrderStatus::Cancelled;
+            self.state.update_order(order).await?;
+        }
+        Ok(())
+    }
+
+    /// Fill an incoming order against the book, price-time priority.
+    /// Trades execute at the resting order's price. Returns the order and the
+    /// tokens that changed hands.
+    async fn match_order(&mut self, mut order: LimitOrder) -> Result<(LimitOrder, Amount), String> {
+        let market = self.state.get_market(order.market_id).await?;
+        let current_time = self.runtime.system_time();
+        if !market.can_bet(current_time) {
+            return Err("Market is closed for trading".to_string());
+        }
+
+        let mut makers: Vec<LimitOrder> = self
+            .state
+            .get_open_orders(order.market_id)
+            .await?
+            .into_iter()
+            .filter(|o| o.can_fill(&order))
+            .collect();
+
+        // Open orders are indexed oldest first, so a stable sort keeps time priority
+        match order.side {
+            OrderSide::Buy => makers.sort_by(|a, b| a.price_bps.cmp(&b.price_bps)),
+            OrderSide::Sell => makers.sort_by(|a, b| b.price_bps.cmp(&a.price_bps)),
+        }
+
+        let mut traded = Amount::ZERO;
+
+        for mut maker in makers {
+            let fill = order.remaining().min(maker.remaining());
+            if fill.is_zero() {
+                break;
+            }
+            let cost = order_cost(fill, maker.price_bps);
+
+            let (buyer, seller) = match order.side {
+                OrderSide::Buy => (&mut order, &mut maker),
+                OrderSide::Sell => (&mut maker, &mut order),
+            };
+
+            // Seller is paid out of the buyer's escrow
+            buyer.escrow = buyer.escrow.saturating_sub(cost);
+            let (buyer_owner, seller_owner) = (buyer.owner, seller.owner);
+            self.pay(seller_owner, cost)?;
+            traded = traded.saturating_add(cost);
+
+            // Buyer takes over the stake
+            self.state
+                .place_bet(Bet {
+                    market_id: order.market_id,
+                    bettor: buyer_owner,
+                    prediction: order.outcome,
+                    amount: fill,
+                    placed_at: current_time,
+                    claimed: false,
+                })
+                .await?;
+            self.state.count_bet(&buyer_owner).await?;
+
+            order.filled = order.filled.saturating_add(fill);
+            maker.filled = maker.filled.saturating_add(fill);
+
+            if maker.remaining().is_zero() {
+                self.release_escrow(&mut maker).await?;
+                maker.status = OrderStatus::Filled;
+            }
+            self.state.update_order(maker).await?;
+        }
+
+        // Refund any price improvement beyond what the rest of the order needs
+        if order.side == OrderSide::Buy {
+            let needed = order_cost(order.remaining(), order.price_bps);
+            let excess = order.escrow.saturating_sub(needed);
+            self.pay(order.owner, excess)?;
+            order.escrow = order.escrow.saturating_sub(excess);
+        }
+
+        if order.remaining().is_zero() {
+            order.status = OrderStatus::Filled;
+        }
+
+        Ok((order, traded))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use futures::FutureExt as _;
+    use linera_sdk::{
+        linera_base_types::{
+            AccountOwner, Amount, ApplicationId, ChainId, ChainOwnership, CryptoHash, Timestamp,
+        },
+        util::BlockingWait,
+        views::View,
+        Contract, ContractRuntime,
+    };
+    use market::{MarketOperation, MarketParameters, MarketResponse};
+    use oracle_ai_abi::{
+        LimitOrder, MarketCategory, MarketLimits, OracleMode, OrderSide, OrderStatus, Outcome,
+        SignedAmount,
+    };
+    use token::{TokenOperation, TokenResponse};
+
+    use super::{MarketContract, MarketState};
+
+    const ADMIN: AccountOwner = AccountOwner::Address20([1; 20]);
+    const ALICE: AccountOwner = AccountOwner::Address20([2; 20]);
+    const BOB: AccountOwner = AccountOwner::Address20([3; 20]);
+
+    /// Calls the market made to the token app
+    type TokenCalls = Rc<RefCell<Vec<TokenOperation>>>;
+
+    #[test]
+    fn selling_an_underdog_position_realizes_a_loss() {
+        let (mut market, token_calls) = create_and_instantiate_market(MarketLimits::default());
+        let market_id = create_market(&mut market, ADMIN, None);
+        assert_ok(execute(&mut market, ALICE, bet(market_id, Outcome::Up, 90)));
+        assert_ok(execute(&mut market, BOB, bet(market_id, Outcome::Down, 10)));
+
+        let sell = |min_price_bps| MarketOperation::SellPosition {
+            market_id,
+            side: Outcome::Down,
+            amount: Amount::from_tokens(10),
+            min_price_bps,
+        };
+        // Without bids there is nobody to sell to
+        assert_error(execute(&mut market, BOB, sell(0)));
+
+        // Late in the market the underdog stake only fetches 20% of its face value
+        let bid = MarketOperation::PlaceLimitOrder {
+            market_id,
+            outcome: Outcome::Down,
+            side: OrderSide::Buy,
+            price_bps: 2_000,
+            quantity: Amount::from_tokens(10),
+        };
+        assert_order_id(execute(&mut market, ALICE, bid));
+        assert_error(execute(&mut market, BOB, sell(2_500)));
+        match execute(&mut market, BOB, sell(1_000)) {
+            MarketResponse::Payout(proceeds) => assert_eq!(proceeds, Amount::from_tokens(2)),
+            response => panic!("Unexpected response: {:?}", response),
+        }
+        assert_eq!(paid_to(&token_calls, BOB), Amount::from_tokens(2));
+
+        let history = market
+            .state
+            .position_history
+            .get(&BOB)
+            .now_or_never()
+            .unwrap()
+            .unwrap()
+            .expect("Sale should be recorded");
+        assert_eq!(
+            history[0].realized_pnl,
+            SignedAmount::difference(Amount::from_tokens(2), Amount::from_tokens(10))
+        );
+
+        // The stake changed hands; the pools the winners are paid from did not move
+        let state = &market.state;
+        let pools = state.get_market(market_id).now_or_never().unwrap().unwrap();
+        assert_eq!(pools.down_pool, Amount::from_tokens(10));
+        assert_eq!(pools.total_pool, Amount::from_tokens(100));
+        assert_eq!(
+            side_stake(state, market_id, BOB, Outcome::Down),
+            Amount::ZERO
+        );
+        assert_eq!(
+            side_stake(state, market_id, ALICE, Outcome::Down),
+            Amount::from_tokens(10)
+        );
+    }
+
+    #[test]
+    fn orders_fill_by_price_then_time_with_partial_fills() {
+        let (mut market, token_calls) = create_and_instantiate_market(MarketLimits::default());
+        let market_id = create_market(&mut market, ADMIN, None);
+        assert_ok(execute(&mut market, BOB, bet(market_id, Outcome::Up, 20)));
+        assert_ok(execute(&mut market, ADMIN, bet(market_id, Outcome::Up, 10)));
+
+        let ask = |price_bps| limit_order(market_id, OrderSide::Sell, price_bps, 10);
+        let dear = assert_order_id(execute(&mut market, BOB, ask(6_000)));
+        let cheap = assert_order_id(execute(&mut market, BOB, ask(5_000)));
+        let later = assert_order_id(execute(&mut market, ADMIN, ask(5_000)));
+
+        // The cheapest asks fill first, and the older one of the two at the same price
+        let bid = limit_order(market_id, OrderSide::Buy, 6_000, 15);
+        let taker = assert_order_id(execute(&mut market, ALICE, bid));
+
+        let state = &market.state;
+        assert_eq!(order(state, cheap).status, OrderStatus::Filled);
+        assert_eq!(order(state, later).status, OrderStatus::Open);
+        assert_eq!(order(state, later).filled, Amount::from_tokens(5));
+        assert_eq!(order(state, dear).filled, Amount::ZERO);
+        assert_eq!(order(state, taker).status, OrderStatus::Filled);
+        assert_eq!(
+            side_stake(state, market_id, ALICE, Outcome::Up),
+            Amount::from_tokens(15)
+        );
+
+        // Trades settle at the resting price; the buyer gets the improvement back
+        assert_eq!(paid_to(&token_calls, BOB), Amount::from_tokens(5));
+        assert_eq!(paid_to(&token_calls, ADMIN), Amount::from_millis(2_500));
+        assert_eq!(paid_to(&token_calls, ALICE), Amount::from_millis(1_500));
+    }
+
+    #[test]
+    fn cancelling_an_order_releases_its_escrow() {
+        let (mut market, token_calls) = create_and_instantiate_market(MarketLimits::default());
+        let market_id = create_market(&mut market, ADMIN, None);
+        assert_ok(execute(&mut market, BOB, bet(market_id, Outcome::Up, 10)));
+
+        let bid = limit_order(market_id, OrderSide::Buy, 4_000, 10);
+        let bid = assert_order_id(execute(&mut market, ALICE, bid));
+        let ask = limit_order(market_id, OrderSide::Sell, 7_000, 10);
+        let ask = assert_order_id(execute(&mut market, BOB, ask));
+        assert_eq!(
+            side_stake(&market.state, market_id, BOB, Outcome::Up),
+            Amount::ZERO
+        );
+
+        let cancel = |order_id| MarketOperation::CancelOrder { order_id };
+        assert_error(execute(&mut market, BOB, cancel(bid)));
+        assert_ok(execute(&mut market, ALICE, cancel(bid)));
+        assert_ok(execute(&mut market, BOB, cancel(ask)));
+        assert_error(execute(&mut market, BOB, cancel(ask)));
+
+        assert_eq!(paid_to(&token_calls, ALICE), Amount::from_tokens(4));
+        assert_eq!(
+            side_stake(&market.state, market_id, BOB, Outcome::Up),
+            Amount::from_tokens(10)
+        );
+        let depth = market
+            .state
+            .depth(market_id, Outcome::Up)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert!(depth.bids.is_empty() && depth.asks.is_empty());
+    }
+
+    #[test]
+    fn resolving_a_market_cancels_its_open_orders() {
+        let (mut market, token_calls) = create_and_instantiate_market(MarketLimits::default());
+        let market_id = create_market(&mut market, ADMIN, None);
+        assert_ok(execute(&mut market, BOB, bet(market_id, Outcome::Up, 10)));
+
+        let ask = limit_order(market_id, OrderSide::Sell, 8_000, 10);
+        let ask = assert_order_id(execute(&mut market, BOB, ask));
+        let bid = limit_order(market_id, OrderSide::Buy, 3_000, 5);
+        let bid = assert_order_id(execute(&mut market, ALICE, bid));
+
+        let resolve = MarketOperation::ResolveMarket {
+            market_id,
+            outcome: Outcome::Up,
+        };
+        assert_ok(execute(&mut market, ADMIN, resolve));
+        assert_eq!(order(&market.state, ask).status, OrderStatus::Cancelled);
+        assert_eq!(order(&market.state, bid).status, OrderStatus::Cancelled);
+        assert_eq!(paid_to(&token_calls, ALICE), Amount::from_millis(1_500));
+
+        // The stake that was listed for sale is claimed along with the rest
+        let claim = MarketOperation::ClaimWinnings { market_id };
+        match execute(&mut market, BOB, claim) {
+            MarketResponse::Payout(payout) => assert_eq!(payout, Amount::from_millis(9_500)),
+            response => panic!("Unexpected response: {:?}", response),
+        }
+
+        let late_bid = limit_order(market_id, OrderSide::Buy, 3_000, 5);
+        assert_error(execute(&mut market, ALICE, late_bid));
+    }
+
+    #[test]
+    fn creator_limits_cannot_loosen_the_protocol_caps() {
+        let caps = MarketLimits {
+            max_bet: Some(Amount::from_tokens(50)),
+            max_user_stake: Some(Amount::from_tokens(100)),
+            max_pool: None,
+        };
+        let (mut market, _) = create_and_instantiate_market(caps.clone());
+
+        let uncapped = create_market(&mut market, ADMIN, Some(MarketLimits::default()));
+        let mixed = MarketLimits {
+            max_bet: Some(Amount::from_tokens(500)),
+            max_user_stake: Some(Amount::from_tokens(20)),
+            max_pool: Some(Amount::from_tokens(1_000)),
+        };
+        let mixed = create_market(&mut market, ADMIN, Some(mixed));
+
+        let limits = |id| {
+            let state = &market.state;
+            state.get_market(id).now_or_never().unwrap().unwrap().limits
+        };
+        assert_eq!(limits(uncapped), caps);
+        assert_eq!(
+            limits(mixed),
+            MarketLimits {
+                max_bet: Some(Amount::from_tokens(50)),
+                max_user_stake: Some(Amount::from_tokens(20)),
+                max_pool: Some(Amount::from_tokens(1_000)),
+            }
+        );
+
+        assert_error(execute(&mut market, ALICE, bet(uncapped, Outcome::Up, 60)));
+        assert_ok(execute(&mut market, ALICE, bet(uncapped, Outcome::Up, 50)));
+    }
+
+    #[test]
+    fn only_the_oracle_or_a_manual_creator_resolves() {
+        let (mut market, _) = create_and_instantiate_market(MarketLimits::default());
+        let oracle_app = ApplicationId::new(CryptoHash::from([5; 4]));
+        let resolve = |market_id| MarketOperation::ResolveMarket {
+            market_id,
+            outcome: Outcome::Up,
+        };
+
+        let manual = create_market(&mut market, ALICE, None);
+        assert_error(execute(&mut market, BOB, resolve(manual)));
+        assert_ok(execute(&mut market, ALICE, resolve(manual)));
+
+        let create_ai = MarketOperation::CreateMarket {
+            question: "Up or down?".to_string(),
+            description: String::new(),
+            category: MarketCategory::Crypto,
+            duration_minutes: 60,
+            oracle_mode: OracleMode::AI,
+            limits: None,
+        };
+        let ai = match execute(&mut market, ALICE, create_ai) {
+            MarketResponse::MarketId(id) => id,
+            response => panic!("Unexpected response: {:?}", response),
+        };
+        assert_error(execute(&mut market, ALICE, resolve(ai)));
+
+        let set_oracle = MarketOperation::SetOracle { oracle_app };
+        assert_error(execute(&mut market, BOB, set_oracle.clone()));
+        assert_ok(execute(&mut market, ADMIN, set_oracle));
+        market.runtime.set_authenticated_caller_id(Some(oracle_app));
+        assert_ok(execute(&mut market, BOB, resolve(ai)));
+    }
+
+    fn create_and_instantiate_market(default_limits: MarketLimits) -> (MarketContract, TokenCalls) {
+        let chain_id = ChainId(CryptoHash::from([8; 4]));
+        let token_calls = TokenCalls::default();
+        let recorded = token_calls.clone();
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(MarketParameters {
+                token_app: ApplicationId::new(CryptoHash::from([6; 4])).with_abi(),
+                default_limits,
+            })
+            .with_application_id(ApplicationId::new(CryptoHash::from([7; 4])))
+            .with_chain_id(chain_id)
+            .with_application_creator_chain_id(chain_id)
+            .with_chain_ownership(ChainOwnership::single_super(ADMIN))
+            .with_authenticated_signer(Some(ADMIN))
+            .with_authenticated_caller_id(None)
+            .with_system_time(Timestamp::from(0))
+            .with_call_application_handler(move |_authenticated, _application, operation| {
+                let operation = bcs::from_bytes(&operation).expect("Unexpected token call");
+                recorded.borrow_mut().push(operation);
+                bcs::to_bytes(&TokenResponse::Ok).unwrap()
+            });
+        let mut contract = MarketContract {
+            state: MarketState::load(runtime.root_view_storage_context())
+                .blocking_wait()
+                .expect("Failed to read from mock key value store"),
+            runtime,
+        };
+
+        contract
+            .instantiate(())
+            .now_or_never()
+            .expect("Initialization of market state should not await anything");
+        (contract, token_calls)
+    }
+
+    fn execute(
+        contract: &mut MarketContract,
+        signer: AccountOwner,
+        operation: MarketOperation,
+    ) -> MarketResponse {
+        contract.runtime.set_authenticated_signer(Some(signer));
+        contract
+            .execute_operation(operation)
+            .now_or_never()
+            .expect("Execution of market operation should not await anything")
+    }
+
+    fn create_market(
+        contract: &mut MarketContract,
+        creator: AccountOwner,
+        limits: Option<MarketLimits>,
+    ) -> u64 {
+        let create = MarketOperation::CreateMarket {
+            question: "Up or down?".to_string(),
+            description: String::new(),
+            category: MarketCategory::Crypto,
+            duration_minutes: 60,
+            oracle_mode: OracleMode::Manual,
+            limits,
+        };
+        match execute(contract, creator, create) {
+            MarketResponse::MarketId(id) => id,
+            response => panic!("Unexpected response: {:?}", response),
+        }
+    }
+
+    fn bet(market_id: u64, prediction: Outcome, tokens: u128) -> MarketOperation {
+        MarketOperation::PlaceBet {
+            market_id,
+            prediction,
+            amount: Amount::from_tokens(tokens),
+            min_implied_payout: None,
+            max_pool_share: None,
+        }
+    }
+
+    fn limit_order(
+        market_id: u64,
+        side: OrderSide,
+        price_bps: u32,
+        tokens: u128,
+    ) -> MarketOperation {
+        MarketOperation::PlaceLimitOrder {
+            market_id,
+            outcome: Outcome::Up,
+            side,
+            price_bps,
+            quantity: Amount::from_tokens(tokens),
+        }
+    }
+
+    fn order(state: &MarketState, order_id: u64) -> LimitOrder {
+        state.get_order(order_id).now_or_never().unwrap().unwrap()
+    }
+
+    fn side_stake(
+        state: &MarketState,
+        market_id: u64,
+        owner: AccountOwner,
+        side: Outcome,
+    ) -> Amount {
+        state
+            .side_stake_of(market_id, &owner, side)
+            .now_or_never()
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Tokens the market has paid out of escrow to `owner`
+    fn paid_to(token_calls: &TokenCalls, owner: AccountOwner) -> Amount {
+        token_calls
+            .borrow()
+            .iter()
+            .filter_map(|call| match call {
+                TokenOperation::ApplicationTransfer { to, amount } if *to == owner => Some(*amount),
+                TokenOperation::ApplicationTransferToAccount { to, amount }
+                    if to.owner == owner =>
+                {
+                    Some(*amount)
+                }
+                _ => None,
+            })
+            .fold(Amount::ZERO, |acc, amount| acc.saturating_add(amount))
+    }
+
+    fn assert_ok(response: MarketResponse) {
+        assert!(
+            matches!(response, MarketResponse::Ok),
+            "Unexpected response: {:?}",
+            response
+        );
+    }
+
+    fn assert_order_id(response: MarketResponse) -> u64 {
+        match response {
+            MarketResponse::OrderId(id) => id,
+            response => panic!("Unexpected response: {:?}", response),
+        }
+    }
+
+    fn assert_error(response: MarketResponse) {
+        assert!(
+            matches!(response, MarketResponse::Error(_)),
+            "Unexpected response: {:?}",
+            response
+        );
+    }
+}
@@ -1,6 +1,6 @@
-use async_graphql::{Request, Response};
+use async_graphql::{Enum, InputObject, Request, Response, SimpleObject};
 use linera_sdk::{
-    linera_base_types::{AccountOwner, Amount, ApplicationId, Timestamp},
+    linera_base_types::{AccountOwner, Amount, ApplicationId, ChainId, Timestamp},
     abi::{ContractAbi, ServiceAbi},
     graphql::GraphQLMutationRoot,
 };
@@ -28,18 +28,60 @@ pub enum MarketOperation {
         category: MarketCategory,
         duration_minutes: u64,
         oracle_mode: OracleMode,
+        /// Exposure caps, tightened to the protocol caps; those apply when omitted
+        limits: Option<MarketLimits>,
     },
     /// Place a bet on a market
     PlaceBet {
         market_id: u64,
         prediction: Outcome,
         amount: Amount,
+        /// Reject the bet if, after it, winning would pay less than this
+        min_implied_payout: Option<Amount>,
+        /// Reject the bet if, after it, the chosen side holds more than this
+        /// share of the total pool (basis points)
+        max_pool_share: Option<u32>,
     },
-    /// Resolve a market (oracle or creator only)
+    /// Sell (part of) a position to the best resting bids before the market closes.
+    /// Sells only what the book can take at `min_price_bps` or better.
+    SellPosition {
+        market_id: u64,
+        side: Outcome,
+        amount: Amount,
+        min_price_bps: u32,
+    },
+    /// Post a limit order for stake in one outcome
+    PlaceLimitOrder {
+        market_id: u64,
+        outcome: Outcome,
+        side: OrderSide,
+        price_bps: u32,
+        quantity: Amount,
+    },
+    /// Cancel an open limit order and release its escrow
+    CancelOrder {
+        order_id: u64,
+    },
+    /// Deposit liquidity into a market in exchange for LP shares
+    ProvideLiquidity {
+        market_id: u64,
+        amount: Amount,
+    },
+    /// Redeem LP shares for their share of the market's liquidity
+    WithdrawLiquidity {
+        market_id: u64,
+        shares: Amount,
+    },
+    /// Resolve a market (the oracle app, or the creator of a manual market)
     ResolveMarket {
         market_id: u64,
         outcome: Outcome,
     },
+    /// Close a market for trading once its closing time has passed (anyone).
+    /// Open orders are cancelled here, and when a market is resolved or cancelled.
+    CloseMarket {
+        market_id: u64,
+    },
     /// Claim winnings from a resolved market
     ClaimWinnings {
         market_id: u64,
@@ -48,19 +90,160 @@ pub enum MarketOperation {
     CancelMarket {
         market_id: u64,
     },
+    /// Pay accrued platform fees to token stakers (admin only)
+    DistributeFees,
+    /// Replace the protocol settings (admin only)
+    UpdateConfig {
+        config: MarketConfig,
+    },
+    /// Set the governance application allowed to run admin operations (admin only)
+    SetGovernance {
+        governance_app: ApplicationId,
+    },
+    /// Set the oracle application allowed to resolve markets (admin only)
+    SetOracle {
+        oracle_app: ApplicationId,
+    },
+    /// Read an event from the market stream of `chain_id`, for apps subscribed to it.
+    /// Only callable by applications: the runtime only lets an app read its own streams,
+    /// so subscribers (e.g. the AI agent) read the market's events through this call.
+    ReadEvent {
+        chain_id: ChainId,
+        index: u32,
+    },
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum MarketResponse {
     Ok,
     MarketId(u64),
+    OrderId(u64),
     Payout(Amount),
+    Shares(Amount),
+    Event(MarketEvent),
     Error(String),
 }
 
+/// Cross-chain messages between user chains and the market chain
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum MarketMessage {
+    /// A bet submitted on a user chain, executed on the market chain
+    /// with the bettor's authentication
+    PlaceBet {
+        market_id: u64,
+        prediction: Outcome,
+        amount: Amount,
+        min_implied_payout: Option<Amount>,
+        max_pool_share: Option<u32>,
+    },
+    /// A claim submitted on a user chain
+    ClaimWinnings {
+        market_id: u64,
+    },
+    /// Result of a remote bet or claim, sent back to the user chain
+    Receipt(RemoteReceipt),
+}
+
+/// Outcome of a bet or claim forwarded to the market chain
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct RemoteReceipt {
+    pub market_id: u64,
+    pub owner: AccountOwner,
+    pub kind: ReceiptKind,
+    pub amount: Amount,
+    pub reason: Option<String>,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
+pub enum ReceiptKind {
+    BetAccepted,
+    /// The bet was refused (e.g. it arrived after `closes_at`); no stake was taken
+    BetRefused,
+    /// Winnings were sent to the owner's account on the chain that claimed them
+    Payout,
+    ClaimRefused,
+}
+
+/// Stream on which the market app publishes [`MarketEvent`]s
+pub const MARKET_STREAM_NAME: &[u8] = b"market_events";
+
+/// Events published for live feeds, indexers and other chains
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum MarketEvent {
+    MarketCreated {
+        market_id: u64,
+        creator: AccountOwner,
+        question: String,
+        category: MarketCategory,
+        closes_at: Timestamp,
+    },
+    BetPlaced {
+        market_id: u64,
+        bettor: AccountOwner,
+        prediction: Outcome,
+        amount: Amount,
+        placed_at: Timestamp,
+    },
+    MarketClosed {
+        market_id: u64,
+    },
+    MarketResolved {
+        market_id: u64,
+        outcome: Outcome,
+        resolved_at: Timestamp,
+    },
+    WinningsClaimed {
+        market_id: u64,
+        winner: AccountOwner,
+        amount: Amount,
+    },
+    MarketCancelled {
+        market_id: u64,
+    },
+}
+
+/// Protocol settings that can be changed without redeploying
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject, InputObject)]
+#[graphql(input_name = "MarketConfigInput")]
+pub struct MarketConfig {
+    /// Fee taken from each resolved pool; fixed per market at creation
+    pub platform_fee_percent: u8,
+    /// Caps every market's limits are held within; the parameters' defaults if unset
+    pub default_limits: Option<MarketLimits>,
+    pub min_duration_micros: u64,
+    pub max_duration_micros: u64,
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        Self {
+            platform_fee_percent: PLATFORM_FEE_PERCENT,
+            default_limits: None,
+            min_duration_micros: MIN_MARKET_DURATION_MICROS,
+            max_duration_micros: MAX_MARKET_DURATION_MICROS,
+        }
+    }
+}
+
+impl MarketConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.platform_fee_percent >= 100 {
+            return Err("Platform fee must be below 100%".to_string());
+        }
+        if self.min_duration_micros == 0 || self.min_duration_micros > self.max_duration_micros {
+            return Err("Invalid market duration bounds".to_string());
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MarketParameters {
     pub token_app: ApplicationId<token::TokenAbi>,
+    /// Caps every market's limits are held within, unless the config overrides them
+    #[serde(default)]
+    pub default_limits: MarketLimits,
 }
 
 impl Default for MarketResponse {
@@ -57,6 +57,7 @@ impl MutationRoot {
         category: oracle_ai_abi::MarketCategory,
         duration_minutes: u64,
         oracle_mode: oracle_ai_abi::OracleMode,
+        limits: Option<oracle_ai_abi::MarketLimits>,
     ) -> Vec<u8> {
         let op = market::MarketOperation::CreateMarket {
             question,
@@ -64,6 +65,7 @@ impl MutationRoot {
             category,
             duration_minutes,
             oracle_mode,
+            limits,
         };
         bcs::to_bytes(&op).unwrap()
     }
@@ -73,12 +75,104 @@ impl MutationRoot {
         market_id: u64,
         prediction: oracle_ai_abi::Outcome,
         amount: linera_sdk::linera_base_types::Amount,
+        min_implied_payout: Option<linera_sdk::linera_base_types::Amount>,
+        max_pool_share: Option<u32>,
     ) -> Vec<u8> {
         let op = market::MarketOperation::PlaceBet {
             market_id,
             prediction,
             amount,
+            min_implied_payout,
+            max_pool_share,
         };
         bcs::to_bytes(&op).unwrap()
     }
+
+    async fn close_market(&self, market_id: u64) -> Vec<u8> {
+        let op = market::MarketOperation::CloseMarket { market_id };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn distribute_fees(&self) -> Vec<u8> {
+        let op = market::MarketOperation::DistributeFees;
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn update_config(&self, config: market::MarketConfig) -> Vec<u8> {
+        let op = market::MarketOperation::UpdateConfig { config };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn set_governance(
+        &self,
+        governance_app: linera_sdk::linera_base_types::ApplicationId,
+    ) -> Vec<u8> {
+        let op = market::MarketOperation::SetGovernance { governance_app };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn set_oracle(
+        &self,
+        oracle_app: linera_sdk::linera_base_types::ApplicationId,
+    ) -> Vec<u8> {
+        let op = market::MarketOperation::SetOracle { oracle_app };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn sell_position(
+        &self,
+        market_id: u64,
+        side: oracle_ai_abi::Outcome,
+        amount: linera_sdk::linera_base_types::Amount,
+        min_price_bps: u32,
+    ) -> Vec<u8> {
+        let op = market::MarketOperation::SellPosition {
+            market_id,
+            side,
+            amount,
+            min_price_bps,
+        };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn place_limit_order(
+        &self,
+        market_id: u64,
+        outcome: oracle_ai_abi::Outcome,
+        side: oracle_ai_abi::OrderSide,
+        price_bps: u32,
+        quantity: linera_sdk::linera_base_types::Amount,
+    ) -> Vec<u8> {
+        let op = market::MarketOperation::PlaceLimitOrder {
+            market_id,
+            outcome,
+            side,
+            price_bps,
+            quantity,
+        };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn cancel_order(&self, order_id: u64) -> Vec<u8> {
+        let op = market::MarketOperation::CancelOrder { order_id };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn provide_liquidity(
+        &self,
+        market_id: u64,
+        amount: linera_sdk::linera_base_types::Amount,
+    ) -> Vec<u8> {
+        let op = market::MarketOperation::ProvideLiquidity { market_id, amount };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn withdraw_liquidity(
+        &self,
+        market_id: u64,
+        shares: linera_sdk::linera_base_types::Amount,
+    ) -> Vec<u8> {
+        let op = market::MarketOperation::WithdrawLiquidity { market_id, shares };
+        bcs::to_bytes(&op).unwrap()
+    }
 }
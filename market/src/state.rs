@@ -1,12 +1,17 @@
-use async_graphql::SimpleObject;
+use async_graphql::{ComplexObject, SimpleObject};
 use linera_sdk::{
-    linera_base_types::{AccountOwner, Amount},
+    linera_base_types::{AccountOwner, Amount, ApplicationId},
     views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
 };
-use oracle_ai_abi::{Bet, Market, MarketStatus, Outcome};
+use market::{MarketConfig, RemoteReceipt};
+use oracle_ai_abi::{
+    Bet, DepthLevel, LimitOrder, LiquidityPool, LpPosition, Market, MarketStatus,
+    OrderBookDepth, OrderSide, Outcome, PositionRecord,
+};
 
 #[derive(RootView, SimpleObject)]
 #[view(context = ViewStorageContext)]
+#[graphql(complex)]
 pub struct MarketState {
     /// All markets
     pub markets: MapView<u64, Market>,
@@ -23,6 +28,92 @@ pub struct MarketState {
     /// Claimed winnings tracker
     #[graphql(skip)]
     pub claimed: MapView<(u64, AccountOwner), bool>,
+
+    /// Closed positions per user, with realized PnL
+    pub position_history: MapView<AccountOwner, Vec<PositionRecord>>,
+
+    /// All limit orders, including filled and cancelled ones
+    pub orders: MapView<u64, LimitOrder>,
+
+    /// Open order IDs per market
+    #[graphql(skip)]
+    pub open_orders: MapView<u64, Vec<u64>>,
+
+    /// Next order ID
+    pub next_order_id: RegisterView<u64>,
+
+    /// LP liquidity per market
+    pub liquidity: MapView<u64, LiquidityPool>,
+
+    /// LP shares per (market, provider)
+    #[graphql(skip)]
+    pub lp_shares: MapView<(u64, AccountOwner), Amount>,
+
+    /// Results of bets and claims this chain forwarded to the market chain
+    pub receipts: MapView<AccountOwner, Vec<RemoteReceipt>>,
+
+    /// Number of bets each account has placed or bought through the order book.
+    /// On a user chain, counts the remote bets the market chain accepted.
+    pub bet_counts: MapView<AccountOwner, u64>,
+
+    /// Protocol settings
+    pub config: RegisterView<MarketConfig>,
+
+    /// Governance application allowed to run admin operations
+    pub governance_app: RegisterView<Option<ApplicationId>>,
+
+    /// Oracle application allowed to resolve markets
+    pub oracle_app: RegisterView<Option<ApplicationId>>,
+}
+
+#[ComplexObject]
+impl MarketState {
+    /// A single market, if it exists
+    async fn market(&self, market_id: u64) -> Option<Market> {
+        self.get_market(market_id).await.ok()
+    }
+
+    /// Markets still accepting bets, by ID
+    async fn open_markets(&self) -> async_graphql::Result<Vec<Market>> {
+        let mut markets = Vec::new();
+        for id in self.markets.indices().await? {
+            let market = self.get_market(id).await?;
+            if market.status == MarketStatus::Active {
+                markets.push(market);
+            }
+        }
+        markets.sort_by_key(|market| market.id);
+        Ok(markets)
+    }
+
+    /// Aggregated open orders for one outcome of a market
+    async fn order_book(&self, market_id: u64, outcome: Outcome) -> OrderBookDepth {
+        self.depth(market_id, outcome).await.unwrap_or_default()
+    }
+
+    /// Whether an account has ever placed a bet (used by the token app's bonus guard)
+    async fn has_placed_bet(&self, owner: AccountOwner) -> bool {
+        self.bet_count(&owner).await > 0
+    }
+
+    /// A provider's LP shares in a market and what they are worth now
+    async fn lp_position(
+        &self,
+        market_id: u64,
+        owner: AccountOwner,
+    ) -> async_graphql::Result<LpPosition> {
+        let market = self.get_market(market_id).await?;
+        let pool = self.get_liquidity(market_id).await?;
+        let shares = self.lp_shares_of(market_id, &owner).await;
+        let value = pool.portion(pool.net_asset_value(&market), shares);
+
+        Ok(LpPosition {
+            market_id,
+            owner,
+            shares,
+            value,
+        })
+    }
 }
 
 impl MarketState {
@@ -90,6 +181,15 @@ impl MarketState {
         bettor: &AccountOwner,
     ) -> Result<Amount, String> {
         let market = self.get_market(market_id).await?;
+        let bets = self.get_bets(market_id).await?;
+
+        // Cancelled markets refund every stake
+        if market.status == MarketStatus::Cancelled {
+            return Ok(bets
+                .iter()
+                .filter(|b| b.bettor == *bettor)
+                .fold(Amount::ZERO, |acc, b| acc.saturating_add(b.amount)));
+        }
 
         if market.status != MarketStatus::Resolved {
             return Err("Market not resolved yet".to_string());
@@ -99,17 +199,15 @@ impl MarketState {
             .outcome
             .ok_or_else(|| "Market has no outcome".to_string())?;
 
-        let bets = self.get_bets(market_id).await?;
-
-        // Find user's winning bet
-        let winning_bet = bets
+        // Sum the user's winning stakes
+        let stake = bets
             .iter()
-            .find(|b| b.bettor == *bettor && b.prediction == outcome);
+            .filter(|b| b.bettor == *bettor && b.prediction == outcome)
+            .fold(Amount::ZERO, |acc, b| acc.saturating_add(b.amount));
 
-        let bet = match winning_bet {
-            Some(b) => b,
-            None => return Ok(Amount::ZERO),
-        };
+        if stake.is_zero() {
+            return Ok(Amount::ZERO);
+        }
 
         let winning_pool = match outcome {
             Outcome::Up => market.up_pool,
@@ -122,13 +220,82 @@ impl MarketState {
 
         // Payout = (user_bet / winning_pool) * total_pool * 0.95
         // Platform takes 5%
-        let total_u128 = u128::from(market.total_pool);
-        let bet_u128 = u128::from(bet.amount);
-        let winning_u128 = u128::from(winning_pool);
+        Ok(market.payout_for(stake, winning_pool))
+    }
+
+    /// Total stake a bettor holds in a market, on either side
+    pub async fn stake_of(&self, market_id: u64, bettor: &AccountOwner) -> Result<Amount, String> {
+        Ok(self
+            .get_bets(market_id)
+            .await?
+            .iter()
+            .filter(|b| b.bettor == *bettor)
+            .fold(Amount::ZERO, |acc, b| acc.saturating_add(b.amount)))
+    }
+
+    /// Stake a bettor holds in a market on one side
+    pub async fn side_stake_of(
+        &self,
+        market_id: u64,
+        bettor: &AccountOwner,
+        side: Outcome,
+    ) -> Result<Amount, String> {
+        Ok(self
+            .get_bets(market_id)
+            .await?
+            .iter()
+            .filter(|b| b.bettor == *bettor && b.prediction == side)
+            .fold(Amount::ZERO, |acc, b| acc.saturating_add(b.amount)))
+    }
 
-        let payout_u128 = (bet_u128 * total_u128 * 95) / (winning_u128 * 100);
+    /// Remove `amount` of a bettor's stake on `side`, oldest bets first
+    pub async fn reduce_stake(
+        &mut self,
+        market_id: u64,
+        bettor: &AccountOwner,
+        side: Outcome,
+        amount: Amount,
+    ) -> Result<(), String> {
+        let held = self.side_stake_of(market_id, bettor, side).await?;
+        if held < amount {
+            return Err(format!("Insufficient position: {} < {}", held, amount));
+        }
+
+        let mut bets = self.get_bets(market_id).await?;
+        let mut remaining = amount;
+        for bet in bets
+            .iter_mut()
+            .filter(|b| b.bettor == *bettor && b.prediction == side)
+        {
+            if remaining.is_zero() {
+                break;
+            }
+            let taken = remaining.min(bet.amount);
+            bet.amount = bet.amount.saturating_sub(taken);
+            remaining = remaining.saturating_sub(taken);
+        }
+        bets.retain(|b| !b.amount.is_zero());
 
-        Ok(Amount::from_attos(payout_u128))
+        self.bets
+            .insert(&market_id, bets)
+            .map_err(|e| format!("Failed to save bets: {}", e))
+    }
+
+    /// Append a closed position to the owner's history
+    pub async fn record_position(&mut self, record: PositionRecord) -> Result<(), String> {
+        let mut history = self
+            .position_history
+            .get(&record.owner)
+            .await
+            .map_err(|e| format!("Failed to get position history: {}", e))?
+            .unwrap_or_default();
+
+        let owner = record.owner;
+        history.push(record);
+
+        self.position_history
+            .insert(&owner, history)
+            .map_err(|e| format!("Failed to save position history: {}", e))
     }
 
     /// Check if user has claimed
@@ -146,4 +313,176 @@ impl MarketState {
             .insert(&(market_id, bettor), true)
             .map_err(|e| format!("Failed to mark claimed: {}", e))
     }
-}
\ No newline at end of file
+
+    /// Store a new open order
+    pub async fn create_order(&mut self, order: LimitOrder) -> Result<u64, String> {
+        let id = *self.next_order_id.get();
+
+        let mut order_with_id = order;
+        order_with_id.id = id;
+
+        let mut open = self.open_order_ids(order_with_id.market_id).await?;
+        open.push(id);
+        self.open_orders
+            .insert(&order_with_id.market_id, open)
+            .map_err(|e| format!("Failed to index order: {}", e))?;
+
+        self.orders
+            .insert(&id, order_with_id)
+            .map_err(|e| format!("Failed to create order: {}", e))?;
+
+        self.next_order_id.set(id + 1);
+        Ok(id)
+    }
+
+    /// Get an order by ID
+    pub async fn get_order(&self, id: u64) -> Result<LimitOrder, String> {
+        self.orders
+            .get(&id)
+            .await
+            .map_err(|e| format!("Failed to get order: {}", e))?
+            .ok_or_else(|| format!("Order {} not found", id))
+    }
+
+    /// Update an order, dropping it from the book once it is no longer open
+    pub async fn update_order(&mut self, order: LimitOrder) -> Result<(), String> {
+        if !order.is_open() {
+            let mut open = self.open_order_ids(order.market_id).await?;
+            open.retain(|id| *id != order.id);
+            self.open_orders
+                .insert(&order.market_id, open)
+                .map_err(|e| format!("Failed to index order: {}", e))?;
+        }
+
+        self.orders
+            .insert(&order.id.clone(), order)
+            .map_err(|e| format!("Failed to update order: {}", e))
+    }
+
+    /// IDs of open orders on a market, oldest first
+    pub async fn open_order_ids(&self, market_id: u64) -> Result<Vec<u64>, String> {
+        Ok(self
+            .open_orders
+            .get(&market_id)
+            .await
+            .map_err(|e| format!("Failed to get orders: {}", e))?
+            .unwrap_or_default())
+    }
+
+    /// Open orders on a market, oldest first
+    pub async fn get_open_orders(&self, market_id: u64) -> Result<Vec<LimitOrder>, String> {
+        let mut orders = Vec::new();
+        for id in self.open_order_ids(market_id).await? {
+            orders.push(self.get_order(id).await?);
+        }
+        Ok(orders)
+    }
+
+    /// Aggregate open orders into price levels
+    pub async fn depth(&self, market_id: u64, outcome: Outcome) -> Result<OrderBookDepth, String> {
+        let mut depth = OrderBookDepth::default();
+
+        for order in self.get_open_orders(market_id).await? {
+            if order.outcome != outcome {
+                continue;
+            }
+            let levels = match order.side {
+                OrderSide::Buy => &mut depth.bids,
+                OrderSide::Sell => &mut depth.asks,
+            };
+            match levels.iter_mut().find(|l| l.price_bps == order.price_bps) {
+                Some(level) => {
+                    level.quantity = level.quantity.saturating_add(order.remaining());
+                    level.orders += 1;
+                }
+                None => levels.push(DepthLevel {
+                    price_bps: order.price_bps,
+                    quantity: order.remaining(),
+                    orders: 1,
+                }),
+            }
+        }
+
+        depth.bids.sort_by(|a, b| b.price_bps.cmp(&a.price_bps));
+        depth.asks.sort_by(|a, b| a.price_bps.cmp(&b.price_bps));
+        Ok(depth)
+    }
+
+    /// Number of bets an account has placed
+    pub async fn bet_count(&self, owner: &AccountOwner) -> u64 {
+        self.bet_counts
+            .get(owner)
+            .await
+            .unwrap_or(None)
+            .unwrap_or(0)
+    }
+
+    /// Count a new bet for an account
+    pub async fn count_bet(&mut self, owner: &AccountOwner) -> Result<(), String> {
+        let count = self.bet_count(owner).await;
+        self.bet_counts
+            .insert(owner, count + 1)
+            .map_err(|e| format!("Failed to count bet: {}", e))
+    }
+
+    /// Append a remote receipt to the owner's list
+    pub async fn record_receipt(&mut self, receipt: RemoteReceipt) -> Result<(), String> {
+        let mut receipts = self
+            .receipts
+            .get(&receipt.owner)
+            .await
+            .map_err(|e| format!("Failed to get receipts: {}", e))?
+            .unwrap_or_default();
+
+        let owner = receipt.owner;
+        receipts.push(receipt);
+
+        self.receipts
+            .insert(&owner, receipts)
+            .map_err(|e| format!("Failed to save receipts: {}", e))
+    }
+
+    /// Get a market's LP pool, empty if nobody has provided liquidity
+    pub async fn get_liquidity(&self, market_id: u64) -> Result<LiquidityPool, String> {
+        Ok(self
+            .liquidity
+            .get(&market_id)
+            .await
+            .map_err(|e| format!("Failed to get liquidity: {}", e))?
+            .unwrap_or_else(|| LiquidityPool::new(market_id)))
+    }
+
+    /// Update a market's LP pool
+    pub async fn update_liquidity(&mut self, pool: LiquidityPool) -> Result<(), String> {
+        self.liquidity
+            .insert(&pool.market_id.clone(), pool)
+            .map_err(|e| format!("Failed to update liquidity: {}", e))
+    }
+
+    /// LP shares held by a provider
+    pub async fn lp_shares_of(&self, market_id: u64, owner: &AccountOwner) -> Amount {
+        self.lp_shares
+            .get(&(market_id, *owner))
+            .await
+            .unwrap_or(None)
+            .unwrap_or(Amount::ZERO)
+    }
+
+    /// Set a provider's LP shares
+    pub async fn set_lp_shares(
+        &mut self,
+        market_id: u64,
+        owner: AccountOwner,
+        shares: Amount,
+    ) -> Result<(), String> {
+        if shares.is_zero() {
+            return self
+                .lp_shares
+                .remove(&(market_id, owner))
+                .map_err(|e| format!("Failed to update LP shares: {}", e));
+        }
+        self.lp_shares
+            .insert(&(market_id, owner), shares)
+            .map_err(|e| format!("Failed to update LP shares: {}", e))
+    }
+}
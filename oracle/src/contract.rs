@@ -4,6 +4,7 @@ mod state;
 
 use self::state::OracleState;
 use linera_sdk::{
+    linera_base_types::AccountOwner,
     abi::WithContractAbi,
     views::{RootView, View},
     Contract, ContractRuntime,
@@ -77,6 +78,12 @@ impl Contract for OracleContract {
                     return OracleResponse::Error("Not a registered oracle".to_string());
                 }
 
+                match self.check_quorum(market_id, resolver, outcome).await {
+                    Ok(None) => {}
+                    Ok(Some(pending)) => return pending,
+                    Err(e) => return OracleResponse::Error(e),
+                }
+
                 let resolved_at = self.runtime.system_time();
 
                 let resolution = OracleResolution {
@@ -113,6 +120,12 @@ impl Contract for OracleContract {
                     return OracleResponse::Error("Not a registered oracle".to_string());
                 }
 
+                match self.check_quorum(market_id, resolver, outcome).await {
+                    Ok(None) => {}
+                    Ok(Some(pending)) => return pending,
+                    Err(e) => return OracleResponse::Error(e),
+                }
+
                 let resolved_at = self.runtime.system_time();
 
                 let resolution = OracleResolution {
@@ -148,6 +161,27 @@ impl Contract for OracleContract {
                     Err(e) => OracleResponse::Error(e),
                 }
             }
+
+            OracleOperation::UpdateConfig { config } => {
+                if !self.is_admin() {
+                    return OracleResponse::Error("Only admin can update the config".to_string());
+                }
+                if config.quorum == 0 {
+                    return OracleResponse::Error("Quorum must be at least 1".to_string());
+                }
+
+                self.state.config.set(config);
+                OracleResponse::Ok
+            }
+
+            OracleOperation::SetGovernance { governance_app } => {
+                if !self.is_admin() {
+                    return OracleResponse::Error("Only admin can set governance".to_string());
+                }
+
+                self.state.governance_app.set(Some(governance_app));
+                OracleResponse::Ok
+            }
         }
     }
 
@@ -159,3 +193,34 @@ impl Contract for OracleContract {
         self.state.save().await.expect("Failed to save state");
     }
 }
+
+impl OracleContract {
+    /// Whether the caller is the governance app or the signer is a chain owner
+    fn is_admin(&mut self) -> bool {
+        let governance_app = *self.state.governance_app.get();
+        if governance_app.is_some() && self.runtime.authenticated_caller_id() == governance_app {
+            return true;
+        }
+        match self.runtime.authenticated_signer() {
+            Some(signer) => self.runtime.chain_ownership().super_owners.contains(&signer),
+            None => false,
+        }
+    }
+
+    /// Record a report. Returns the response for a pending report, or `None` once
+    /// enough oracles agree to resolve the market.
+    async fn check_quorum(
+        &mut self,
+        market_id: u64,
+        resolver: AccountOwner,
+        outcome: Outcome,
+    ) -> Result<Option<OracleResponse>, String> {
+        let agreeing = self.state.add_report(market_id, resolver, outcome).await?;
+        let quorum = self.state.config.get().quorum;
+        if agreeing >= quorum {
+            Ok(None)
+        } else {
+            Ok(Some(OracleResponse::ReportRecorded { agreeing, quorum }))
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use async_graphql::{Request, Response};
+use async_graphql::{InputObject, Request, Response, SimpleObject};
 use linera_sdk::{
     linera_base_types::{Amount, ApplicationId, Timestamp},
     abi::{ContractAbi, ServiceAbi},
@@ -38,11 +38,36 @@ pub enum OracleOperation {
 
     /// Register as an oracle
     RegisterOracle,
+
+    /// Replace the resolution settings (admin only)
+    UpdateConfig { config: OracleConfig },
+
+    /// Set the governance application allowed to run admin operations (admin only)
+    SetGovernance { governance_app: ApplicationId },
+}
+
+/// Resolution settings that can be changed without redeploying
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject, InputObject)]
+#[graphql(input_name = "OracleConfigInput")]
+pub struct OracleConfig {
+    /// Number of oracles that must report the same outcome before a market is resolved
+    pub quorum: u32,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self { quorum: 1 }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum OracleResponse {
     Ok,
+    /// The report was recorded; the market resolves once `quorum` reports agree
+    ReportRecorded {
+        agreeing: u32,
+        quorum: u32,
+    },
     Error(String),
 }
 
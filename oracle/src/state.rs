@@ -1,9 +1,10 @@
 use async_graphql::SimpleObject;
 use linera_sdk::{
-    linera_base_types::{AccountOwner, Amount},
+    linera_base_types::{AccountOwner, Amount, ApplicationId},
     views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
 };
-use oracle_ai_abi::OracleResolution;
+use oracle::OracleConfig;
+use oracle_ai_abi::{OracleResolution, Outcome};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
@@ -13,6 +14,13 @@ pub struct PriceFeed {
     pub timestamp: u64,
 }
 
+/// One oracle's vote on a market outcome
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct ResolutionReport {
+    pub oracle: AccountOwner,
+    pub outcome: Outcome,
+}
+
 #[derive(RootView, SimpleObject)]
 #[view(context = ViewStorageContext)]
 pub struct OracleState {
@@ -27,6 +35,15 @@ pub struct OracleState {
 
     /// Resolution count
     pub resolution_count: RegisterView<u64>,
+
+    /// Outcome reports per market, until the quorum is reached
+    pub reports: MapView<u64, Vec<ResolutionReport>>,
+
+    /// Resolution settings
+    pub config: RegisterView<OracleConfig>,
+
+    /// Governance application allowed to run admin operations
+    pub governance_app: RegisterView<Option<ApplicationId>>,
 }
 
 impl OracleState {
@@ -79,4 +96,44 @@ impl OracleState {
             .unwrap_or(None)
             .unwrap_or(false)
     }
+
+    /// Record an oracle's report and return how many reports agree with it
+    pub async fn add_report(
+        &mut self,
+        market_id: u64,
+        oracle: AccountOwner,
+        outcome: Outcome,
+    ) -> Result<u32, String> {
+        let resolved = self
+            .resolutions
+            .contains_key(&market_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        if resolved {
+            return Err("Market already resolved".to_string());
+        }
+
+        let mut reports = self
+            .reports
+            .get(&market_id)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+
+        if reports.iter().any(|report| report.oracle == oracle) {
+            return Err("Oracle already reported on this market".to_string());
+        }
+
+        reports.push(ResolutionReport { oracle, outcome });
+        let agreeing = reports
+            .iter()
+            .filter(|report| report.outcome == outcome)
+            .count() as u32;
+
+        self.reports
+            .insert(&market_id, reports)
+            .map_err(|e| format!("Failed to record report: {}", e))?;
+
+        Ok(agreeing)
+    }
 }
@@ -4,12 +4,17 @@ mod state;
 
 use self::state::TokenState;
 use linera_sdk::{
-    linera_base_types::{AccountOwner, Amount},
+    linera_base_types::{Account, AccountOwner, Amount, ApplicationId, TimeDelta, Timestamp},
     abi::WithContractAbi,
     views::{RootView, View},
     Contract, ContractRuntime,
 };
-use token::{DailyBonus, TokenOperation, TokenParameters, TokenResponse};
+use async_graphql::Request;
+use oracle_ai_abi::{DAILY_BONUS_COOLDOWN_MICROS, STAKE_UNBONDING_PERIOD_MICROS};
+use token::{
+    Allowance, BonusAccess, DailyBonus, EntryDirection, LedgerEntry, LedgerKind, MarketQueryAbi,
+    TokenMessage, TokenOperation, TokenParameters, TokenResponse, VestingSchedule,
+};
 
 pub struct TokenContract {
     state: TokenState,
@@ -23,7 +28,7 @@ impl WithContractAbi for TokenContract {
 }
 
 impl Contract for TokenContract {
-    type Message = ();
+    type Message = TokenMessage;
     type Parameters = TokenParameters;
     type InstantiationArgument = ();
     type EventValue = ();
@@ -41,9 +46,17 @@ impl Contract for TokenContract {
         // Mint initial supply to all super owners
         for owner in self.runtime.chain_ownership().super_owners.iter() {
             self.state
-                .credit(owner, params.initial_supply)
+                .mint(owner, params.initial_supply, params.max_supply)
                 .await
                 .expect("Failed to mint initial supply");
+            self.record(
+                *owner,
+                LedgerKind::Mint,
+                EntryDirection::Credit,
+                params.initial_supply,
+                None,
+            )
+            .await;
         }
     }
 
@@ -55,12 +68,120 @@ impl Contract for TokenContract {
                     .authenticated_signer()
                     .expect("Transfer must be authenticated");
 
+                // A transfer made by an application on the signer's behalf is a deposit into it
+                let kind = match self.runtime.authenticated_caller_id() {
+                    Some(_) => LedgerKind::Escrow,
+                    None => LedgerKind::Transfer,
+                };
+
+                match self.state.transfer(&from, &to, amount).await {
+                    Ok(_) => {
+                        self.record_transfer(kind, from, to, amount).await;
+                        TokenResponse::Ok
+                    }
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
+            TokenOperation::TransferToAccount { to, amount } => {
+                let from = self
+                    .runtime
+                    .authenticated_signer()
+                    .expect("Transfer must be authenticated");
+
+                match self
+                    .transfer_to_account(from, to, amount, LedgerKind::Transfer)
+                    .await
+                {
+                    Ok(_) => TokenResponse::Ok,
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
+            TokenOperation::ApplicationTransfer { to, amount } => {
+                let from = match self.runtime.authenticated_caller_id() {
+                    Some(app) => AccountOwner::from(app),
+                    None => {
+                        return TokenResponse::Error(
+                            "Must be called by an application".to_string(),
+                        )
+                    }
+                };
+
                 match self.state.transfer(&from, &to, amount).await {
+                    Ok(_) => {
+                        self.record_transfer(LedgerKind::Payout, from, to, amount).await;
+                        TokenResponse::Ok
+                    }
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
+            TokenOperation::ApplicationTransferToAccount { to, amount } => {
+                let from = match self.runtime.authenticated_caller_id() {
+                    Some(app) => AccountOwner::from(app),
+                    None => {
+                        return TokenResponse::Error(
+                            "Must be called by an application".to_string(),
+                        )
+                    }
+                };
+
+                match self
+                    .transfer_to_account(from, to, amount, LedgerKind::Payout)
+                    .await
+                {
+                    Ok(_) => TokenResponse::Ok,
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
+            TokenOperation::Approve {
+                spender,
+                amount,
+                expires_at,
+            } => {
+                // Applications approve spending from their own account
+                let owner = match self.runtime.authenticated_caller_id() {
+                    Some(app) => AccountOwner::from(app),
+                    None => match self.runtime.authenticated_signer() {
+                        Some(signer) => signer,
+                        None => return TokenResponse::Error("Must be authenticated".to_string()),
+                    },
+                };
+
+                let allowance = Allowance { amount, expires_at };
+                match self.state.approve(owner, spender, allowance).await {
                     Ok(_) => TokenResponse::Ok,
                     Err(e) => TokenResponse::Error(e),
                 }
             }
 
+            TokenOperation::TransferFrom { owner, to, amount } => {
+                // Applications spend under their own identity
+                let spender = match self.runtime.authenticated_caller_id() {
+                    Some(app) => AccountOwner::from(app),
+                    None => match self.runtime.authenticated_signer() {
+                        Some(signer) => signer,
+                        None => return TokenResponse::Error("Must be authenticated".to_string()),
+                    },
+                };
+
+                let current_time = self.runtime.system_time();
+                match self
+                    .state
+                    .transfer_from(spender, &owner, &to, amount, current_time)
+                    .await
+                {
+                    Ok(_) => {
+                        self.record_transfer(LedgerKind::TransferFrom, owner, to, amount)
+                            .await;
+                        TokenResponse::Ok
+                    }
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
             TokenOperation::ClaimBonus => {
                 let owner = self
                     .runtime
@@ -78,17 +199,36 @@ impl Contract for TokenContract {
                     .unwrap_or(None)
                     .unwrap_or_else(DailyBonus::new);
 
-                let bonus_amount = bonus.claim(current_time);
+                let schedule = self.runtime.application_parameters().bonus_schedule;
+                let bonus_amount = bonus.claim(current_time, &schedule);
 
                 if bonus_amount.is_zero() {
                     return TokenResponse::Error("Bonus not ready yet".to_string());
                 }
 
-                // Credit the bonus
-                self.state
-                    .credit(&owner, bonus_amount)
+                if let Err(e) = self
+                    .check_bonus_guards(&owner, bonus_amount, current_time)
                     .await
-                    .expect("Failed to credit bonus");
+                {
+                    return TokenResponse::Error(e);
+                }
+
+                // Bonuses are newly minted tokens
+                let max_supply = self.runtime.application_parameters().max_supply;
+                if let Err(e) = self.state.mint(&owner, bonus_amount, max_supply).await {
+                    return TokenResponse::Error(e);
+                }
+                self.record(
+                    owner,
+                    LedgerKind::Bonus,
+                    EntryDirection::Credit,
+                    bonus_amount,
+                    None,
+                )
+                .await;
+
+                let day = current_time.micros() / DAILY_BONUS_COOLDOWN_MICROS;
+                self.state.add_bonus_emission(day, bonus_amount);
 
                 // Update bonus record
                 self.state
@@ -99,28 +239,883 @@ impl Contract for TokenContract {
                 TokenResponse::Ok
             }
 
-            TokenOperation::Mint { to, amount } => {
-                // Only super owners can mint
-                let ownership = self.runtime.chain_ownership();
-                let caller = self.runtime.authenticated_signer().unwrap();
+            TokenOperation::SetBonusGuards { guards } => {
+                if !self.is_admin() {
+                    return TokenResponse::Error("Only owner can set bonus guards".to_string());
+                }
 
-                if !ownership.super_owners.contains(&caller) {
-                    return TokenResponse::Error("Only owner can mint".to_string());
+                self.state.bonus_guards.set(guards);
+                TokenResponse::Ok
+            }
+
+            TokenOperation::SetBonusAccess { owner, access } => {
+                if !self.is_admin() {
+                    return TokenResponse::Error("Only owner can set bonus access".to_string());
+                }
+
+                match self.state.set_bonus_access(&owner, access).await {
+                    Ok(_) => TokenResponse::Ok,
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
+            TokenOperation::CreateVesting {
+                beneficiary,
+                amount,
+                start,
+                cliff_micros,
+                duration_micros,
+            } => {
+                if !self.is_admin() {
+                    return TokenResponse::Error("Only owner can create vesting grants".to_string());
+                }
+                // Governance funds grants from its own account
+                let grantor = match self.runtime.authenticated_caller_id() {
+                    Some(app) => AccountOwner::from(app),
+                    None => match self.runtime.authenticated_signer() {
+                        Some(signer) => signer,
+                        None => return TokenResponse::Error("Must be authenticated".to_string()),
+                    },
+                };
+
+                if amount.is_zero() {
+                    return TokenResponse::Error("Nothing to vest".to_string());
+                }
+                if duration_micros == 0 || cliff_micros > duration_micros {
+                    return TokenResponse::Error(
+                        "Cliff must not be longer than a non-zero duration".to_string(),
+                    );
+                }
+
+                // Granted tokens are held by the token app until claimed
+                let vault = self.vault_account();
+                if let Err(e) = self.state.transfer(&grantor, &vault, amount).await {
+                    return TokenResponse::Error(e);
+                }
+                self.record(
+                    grantor,
+                    LedgerKind::Vesting,
+                    EntryDirection::Debit,
+                    amount,
+                    Some(beneficiary),
+                )
+                .await;
+
+                let schedule = VestingSchedule {
+                    id: 0, // Will be set by create_vesting
+                    beneficiary,
+                    grantor,
+                    total: amount,
+                    claimed: Amount::ZERO,
+                    start,
+                    cliff_micros,
+                    duration_micros,
+                    revoked_at: None,
+                };
+
+                match self.state.create_vesting(schedule).await {
+                    Ok(_) => TokenResponse::Ok,
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
+            TokenOperation::ClaimVested => {
+                let beneficiary = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return TokenResponse::Error("Must be authenticated".to_string()),
+                };
+
+                let schedules = match self.state.get_vesting_schedules(&beneficiary).await {
+                    Ok(s) => s,
+                    Err(e) => return TokenResponse::Error(e),
+                };
+
+                let current_time = self.runtime.system_time();
+                let mut total = Amount::ZERO;
+                for mut schedule in schedules {
+                    let claimable = schedule.claimable_at(current_time);
+                    if claimable.is_zero() {
+                        continue;
+                    }
+                    schedule.claimed = schedule.claimed.saturating_add(claimable);
+                    total = total.saturating_add(claimable);
+                    if let Err(e) = self.state.update_vesting(schedule).await {
+                        return TokenResponse::Error(e);
+                    }
+                }
+
+                if total.is_zero() {
+                    return TokenResponse::Error("Nothing has vested yet".to_string());
+                }
+
+                let vault = self.vault_account();
+                if let Err(e) = self.state.transfer(&vault, &beneficiary, total).await {
+                    return TokenResponse::Error(e);
+                }
+                self.record(
+                    beneficiary,
+                    LedgerKind::Vesting,
+                    EntryDirection::Credit,
+                    total,
+                    None,
+                )
+                .await;
+
+                TokenResponse::Balance(total)
+            }
+
+            TokenOperation::RevokeVesting { vesting_id } => {
+                if !self.is_admin() {
+                    return TokenResponse::Error("Only owner can revoke vesting grants".to_string());
+                }
+
+                let mut schedule = match self.state.get_vesting(vesting_id).await {
+                    Ok(s) => s,
+                    Err(e) => return TokenResponse::Error(e),
+                };
+
+                if schedule.revoked_at.is_some() {
+                    return TokenResponse::Error("Vesting grant already revoked".to_string());
+                }
+
+                let current_time = self.runtime.system_time();
+                let unvested = schedule.total.saturating_sub(schedule.vested_at(current_time));
+                schedule.revoked_at = Some(current_time);
+                let grantor = schedule.grantor;
+
+                if let Err(e) = self.state.update_vesting(schedule).await {
+                    return TokenResponse::Error(e);
+                }
+
+                let vault = self.vault_account();
+                if let Err(e) = self.state.transfer(&vault, &grantor, unvested).await {
+                    return TokenResponse::Error(e);
+                }
+                self.record(
+                    grantor,
+                    LedgerKind::Vesting,
+                    EntryDirection::Credit,
+                    unvested,
+                    None,
+                )
+                .await;
+
+                TokenResponse::Balance(unvested)
+            }
+
+            TokenOperation::Stake { amount } => {
+                let owner = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return TokenResponse::Error("Must be authenticated".to_string()),
+                };
+
+                if amount.is_zero() {
+                    return TokenResponse::Error("Nothing to stake".to_string());
+                }
+
+                // Staked tokens are held by the token app until withdrawn
+                let vault = self.vault_account();
+                if let Err(e) = self.state.transfer(&owner, &vault, amount).await {
+                    return TokenResponse::Error(e);
+                }
+                let current_time = self.runtime.system_time();
+                if let Err(e) = self.state.stake(&owner, amount, current_time).await {
+                    return TokenResponse::Error(e);
                 }
+                self.record(owner, LedgerKind::Stake, EntryDirection::Debit, amount, None)
+                    .await;
+
+                TokenResponse::Ok
+            }
+
+            TokenOperation::Unstake { amount } => {
+                let owner = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return TokenResponse::Error("Must be authenticated".to_string()),
+                };
+
+                let unbonding_period = self
+                    .runtime
+                    .application_parameters()
+                    .unbonding_period_micros
+                    .unwrap_or(STAKE_UNBONDING_PERIOD_MICROS);
+                let current_time = self.runtime.system_time();
+                let release_at =
+                    current_time.saturating_add(TimeDelta::from_micros(unbonding_period));
 
-                match self.state.credit(&to, amount).await {
+                match self
+                    .state
+                    .unstake(&owner, amount, current_time, release_at)
+                    .await
+                {
                     Ok(_) => TokenResponse::Ok,
                     Err(e) => TokenResponse::Error(e),
                 }
             }
+
+            TokenOperation::WithdrawUnstaked => {
+                let owner = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return TokenResponse::Error("Must be authenticated".to_string()),
+                };
+
+                let current_time = self.runtime.system_time();
+                let amount = match self.state.take_unbonded(&owner, current_time).await {
+                    Ok(amount) => amount,
+                    Err(e) => return TokenResponse::Error(e),
+                };
+
+                if amount.is_zero() {
+                    return TokenResponse::Error("Nothing has finished unbonding".to_string());
+                }
+
+                let vault = self.vault_account();
+                if let Err(e) = self.state.transfer(&vault, &owner, amount).await {
+                    return TokenResponse::Error(e);
+                }
+                self.record(owner, LedgerKind::Stake, EntryDirection::Credit, amount, None)
+                    .await;
+
+                TokenResponse::Balance(amount)
+            }
+
+            TokenOperation::DistributeRewards { amount } => {
+                let payer = match self.runtime.authenticated_caller_id() {
+                    Some(app) => AccountOwner::from(app),
+                    None => match self.runtime.authenticated_signer() {
+                        Some(signer) => signer,
+                        None => return TokenResponse::Error("Must be authenticated".to_string()),
+                    },
+                };
+
+                if amount.is_zero() {
+                    return TokenResponse::Error("Nothing to distribute".to_string());
+                }
+
+                if self.state.total_staked.get().is_zero() {
+                    return TokenResponse::Error("No tokens are staked".to_string());
+                }
+
+                let vault = self.vault_account();
+                if let Err(e) = self.state.transfer(&payer, &vault, amount).await {
+                    return TokenResponse::Error(e);
+                }
+                if let Err(e) = self.state.distribute_rewards(amount) {
+                    return TokenResponse::Error(e);
+                }
+                self.record(payer, LedgerKind::Reward, EntryDirection::Debit, amount, None)
+                    .await;
+
+                TokenResponse::Ok
+            }
+
+            TokenOperation::ClaimRewards => {
+                let owner = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return TokenResponse::Error("Must be authenticated".to_string()),
+                };
+
+                let rewards = match self.state.take_rewards(&owner).await {
+                    Ok(rewards) => rewards,
+                    Err(e) => return TokenResponse::Error(e),
+                };
+
+                if rewards.is_zero() {
+                    return TokenResponse::Error("No rewards to claim".to_string());
+                }
+
+                let vault = self.vault_account();
+                if let Err(e) = self.state.transfer(&vault, &owner, rewards).await {
+                    return TokenResponse::Error(e);
+                }
+                self.record(owner, LedgerKind::Reward, EntryDirection::Credit, rewards, None)
+                    .await;
+
+                TokenResponse::Balance(rewards)
+            }
+
+            TokenOperation::VotingWeight { owner, at } => {
+                let weight = match at {
+                    Some(at) => self.state.staked_before(&owner, at).await,
+                    None => self
+                        .state
+                        .stake_position(&owner)
+                        .await
+                        .map(|position| position.staked),
+                };
+                match weight {
+                    Ok(weight) => TokenResponse::Balance(weight),
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
+            TokenOperation::SetGovernance { governance_app } => {
+                if !self.is_admin() {
+                    return TokenResponse::Error("Only owner can set governance".to_string());
+                }
+
+                self.state.governance_app.set(Some(governance_app));
+                TokenResponse::Ok
+            }
+
+            TokenOperation::Mint { to, amount } => {
+                if !self.is_admin() {
+                    return TokenResponse::Error("Only owner can mint".to_string());
+                }
+
+                let max_supply = self.runtime.application_parameters().max_supply;
+                match self.state.mint(&to, amount, max_supply).await {
+                    Ok(_) => {
+                        self.record(to, LedgerKind::Mint, EntryDirection::Credit, amount, None)
+                            .await;
+                        TokenResponse::Ok
+                    }
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
+
+            TokenOperation::Burn { amount } => {
+                let owner = match self.runtime.authenticated_signer() {
+                    Some(signer) => signer,
+                    None => return TokenResponse::Error("Must be authenticated".to_string()),
+                };
+
+                match self.state.burn(&owner, amount).await {
+                    Ok(_) => {
+                        self.record(owner, LedgerKind::Burn, EntryDirection::Debit, amount, None)
+                            .await;
+                        TokenResponse::Ok
+                    }
+                    Err(e) => TokenResponse::Error(e),
+                }
+            }
         }
     }
 
-    async fn execute_message(&mut self, _message: Self::Message) {
-        panic!("Token app does not handle messages");
+    async fn execute_message(&mut self, message: Self::Message) {
+        match message {
+            TokenMessage::Credit {
+                source,
+                target,
+                amount,
+            } => {
+                let is_bouncing = self.runtime.message_is_bouncing().unwrap_or_default();
+
+                if is_bouncing {
+                    // Rejected by the receiving chain: restore the sender's balance
+                    self.state
+                        .settle_in_flight(&source, amount)
+                        .await
+                        .expect("Failed to settle in-flight amount");
+                    self.state
+                        .credit(&source, amount)
+                        .await
+                        .expect("Failed to refund bounced transfer");
+                    self.record(
+                        source,
+                        LedgerKind::Refund,
+                        EntryDirection::Credit,
+                        amount,
+                        Some(target),
+                    )
+                    .await;
+                    return;
+                }
+
+                self.state
+                    .receive(&target, amount)
+                    .await
+                    .expect("Failed to credit transfer");
+                self.record(
+                    target,
+                    LedgerKind::CrossChainTransfer,
+                    EntryDirection::Credit,
+                    amount,
+                    Some(source),
+                )
+                .await;
+
+                let origin = self
+                    .runtime
+                    .message_origin_chain_id()
+                    .expect("Incoming message must have an origin chain");
+                self.runtime
+                    .prepare_message(TokenMessage::Ack { source, amount })
+                    .send_to(origin);
+            }
+
+            TokenMessage::Ack { source, amount } => {
+                self.state
+                    .complete_outgoing(&source, amount)
+                    .await
+                    .expect("Failed to settle in-flight amount");
+            }
+        }
     }
 
     async fn store(mut self) {
         self.state.save().await.expect("Failed to save state");
     }
 }
+
+impl TokenContract {
+    /// The token app's own account, holding vesting grants, stakes and rewards
+    fn vault_account(&mut self) -> AccountOwner {
+        AccountOwner::from(self.runtime.application_id().forget_abi())
+    }
+
+    /// Whether the caller is the governance app or the signer is a super owner of this chain
+    fn is_admin(&mut self) -> bool {
+        let governance_app = *self.state.governance_app.get();
+        if governance_app.is_some() && self.runtime.authenticated_caller_id() == governance_app {
+            return true;
+        }
+        match self.runtime.authenticated_signer() {
+            Some(signer) => self.runtime.chain_ownership().super_owners.contains(&signer),
+            None => false,
+        }
+    }
+
+    /// Apply the configured daily bonus guards to a claim
+    async fn check_bonus_guards(
+        &mut self,
+        owner: &AccountOwner,
+        amount: Amount,
+        current_time: Timestamp,
+    ) -> Result<(), String> {
+        let guards = self.state.bonus_guards.get().clone();
+
+        let allowlisted = match self.state.bonus_access_of(owner).await {
+            BonusAccess::Denied => return Err("Account is not eligible for the bonus".to_string()),
+            BonusAccess::Allowed => true,
+            BonusAccess::Default => false,
+        };
+
+        if guards.allowlist_only && !allowlisted {
+            return Err("Account is not on the bonus allowlist".to_string());
+        }
+
+        if let Some(market_app) = guards.require_bet_on {
+            if !allowlisted && !self.has_placed_bet(market_app, owner) {
+                return Err("Place a bet before claiming the bonus".to_string());
+            }
+        }
+
+        if let Some(budget) = guards.daily_emission_budget {
+            let day = current_time.micros() / DAILY_BONUS_COOLDOWN_MICROS;
+            let emitted = self.state.bonus_emitted_on(day);
+            if emitted.saturating_add(amount) > budget {
+                return Err("Daily bonus budget exhausted, try again tomorrow".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ask the market app's service on this chain whether `owner` has ever placed a bet.
+    /// The market app counts remote bets here once the market chain accepts them.
+    fn has_placed_bet(&mut self, market_app: ApplicationId, owner: &AccountOwner) -> bool {
+        let query = Request::new(format!("query {{ hasPlacedBet(owner: \"{}\") }}", owner));
+        let response = self
+            .runtime
+            .query_service(market_app.with_abi::<MarketQueryAbi>(), query);
+
+        response
+            .data
+            .into_json()
+            .ok()
+            .and_then(|data| data["hasPlacedBet"].as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Append an entry to an account's statement
+    async fn record(
+        &mut self,
+        owner: AccountOwner,
+        kind: LedgerKind,
+        direction: EntryDirection,
+        amount: Amount,
+        counterparty: Option<AccountOwner>,
+    ) {
+        let entry = LedgerEntry {
+            kind,
+            direction,
+            amount,
+            counterparty,
+            timestamp: self.runtime.system_time(),
+        };
+        self.state
+            .record(&owner, entry)
+            .await
+            .expect("Failed to record ledger entry");
+    }
+
+    /// Move tokens from `from` to an account on any chain. A transfer on this chain
+    /// is recorded as `local_kind`; others are sent as a tracked credit that is
+    /// refunded if it bounces.
+    async fn transfer_to_account(
+        &mut self,
+        from: AccountOwner,
+        to: Account,
+        amount: Amount,
+        local_kind: LedgerKind,
+    ) -> Result<(), String> {
+        if to.chain_id == self.runtime.chain_id() {
+            self.state.transfer(&from, &to.owner, amount).await?;
+            self.record_transfer(local_kind, from, to.owner, amount).await;
+            return Ok(());
+        }
+
+        self.state.debit(&from, amount).await?;
+        self.state.add_in_flight(&from, amount).await?;
+        self.record(
+            from,
+            LedgerKind::CrossChainTransfer,
+            EntryDirection::Debit,
+            amount,
+            Some(to.owner),
+        )
+        .await;
+
+        let message = TokenMessage::Credit {
+            source: from,
+            target: to.owner,
+            amount,
+        };
+        self.runtime
+            .prepare_message(message)
+            .with_tracking()
+            .send_to(to.chain_id);
+        Ok(())
+    }
+
+    /// Record both sides of a transfer
+    async fn record_transfer(
+        &mut self,
+        kind: LedgerKind,
+        from: AccountOwner,
+        to: AccountOwner,
+        amount: Amount,
+    ) {
+        self.record(from, kind, EntryDirection::Debit, amount, Some(to)).await;
+        self.record(to, kind, EntryDirection::Credit, amount, Some(from)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt as _;
+    use linera_sdk::{
+        linera_base_types::{
+            Account, AccountOwner, Amount, ApplicationId, ChainId, ChainOwnership, CryptoHash,
+            Timestamp,
+        },
+        util::BlockingWait,
+        views::View,
+        Contract, ContractRuntime,
+    };
+    use token::{TokenOperation, TokenParameters, TokenResponse};
+
+    use super::{TokenContract, TokenState};
+
+    const ADMIN: AccountOwner = AccountOwner::Address20([1; 20]);
+    const ALICE: AccountOwner = AccountOwner::Address20([2; 20]);
+    const BOB: AccountOwner = AccountOwner::Address20([3; 20]);
+
+    #[test]
+    fn supply_matches_balances_after_every_operation() {
+        let mut token = create_and_instantiate_token(parameters(None));
+        assert_supply_invariant(&token, Amount::from_tokens(1_000));
+
+        let mint = TokenOperation::Mint {
+            to: ALICE,
+            amount: Amount::from_tokens(500),
+        };
+        assert_ok(execute(&mut token, ADMIN, mint));
+        assert_supply_invariant(&token, Amount::from_tokens(1_500));
+
+        let transfer = TokenOperation::Transfer {
+            to: BOB,
+            amount: Amount::from_tokens(200),
+        };
+        assert_ok(execute(&mut token, ALICE, transfer));
+        assert_supply_invariant(&token, Amount::from_tokens(1_500));
+
+        let stake = TokenOperation::Stake {
+            amount: Amount::from_tokens(100),
+        };
+        assert_ok(execute(&mut token, BOB, stake));
+        assert_supply_invariant(&token, Amount::from_tokens(1_500));
+
+        let vest = TokenOperation::CreateVesting {
+            beneficiary: ALICE,
+            amount: Amount::from_tokens(300),
+            start: Timestamp::from(0),
+            cliff_micros: 0,
+            duration_micros: 1_000,
+        };
+        assert_ok(execute(&mut token, ADMIN, vest));
+        assert_supply_invariant(&token, Amount::from_tokens(1_500));
+
+        token.runtime.set_system_time(Timestamp::from(500));
+        match execute(&mut token, ALICE, TokenOperation::ClaimVested) {
+            TokenResponse::Balance(claimed) => assert_eq!(claimed, Amount::from_tokens(150)),
+            response => panic!("Unexpected response: {:?}", response),
+        }
+        assert_supply_invariant(&token, Amount::from_tokens(1_500));
+
+        let burn = TokenOperation::Burn {
+            amount: Amount::from_tokens(50),
+        };
+        assert_ok(execute(&mut token, ALICE, burn));
+        assert_supply_invariant(&token, Amount::from_tokens(1_450));
+
+        // Tokens sent to another chain stay in this chain's supply until acknowledged
+        let send = TokenOperation::TransferToAccount {
+            to: Account {
+                chain_id: ChainId(CryptoHash::from([9; 4])),
+                owner: BOB,
+            },
+            amount: Amount::from_tokens(25),
+        };
+        assert_ok(execute(&mut token, ALICE, send));
+        assert_eq!(*token.state.total_in_flight.get(), Amount::from_tokens(25));
+        assert_supply_invariant(&token, Amount::from_tokens(1_450));
+    }
+
+    #[test]
+    fn mint_respects_the_supply_cap() {
+        let mut token = create_and_instantiate_token(parameters(Some(Amount::from_tokens(1_200))));
+
+        let within_cap = TokenOperation::Mint {
+            to: ALICE,
+            amount: Amount::from_tokens(200),
+        };
+        assert_ok(execute(&mut token, ADMIN, within_cap));
+
+        let over_cap = TokenOperation::Mint {
+            to: ALICE,
+            amount: Amount::from_attos(1),
+        };
+        assert!(matches!(
+            execute(&mut token, ADMIN, over_cap),
+            TokenResponse::Error(_)
+        ));
+        assert_supply_invariant(&token, Amount::from_tokens(1_200));
+    }
+
+    #[test]
+    fn mint_is_limited_to_owners_and_governance() {
+        let mut token = create_and_instantiate_token(parameters(None));
+        let governance_app = ApplicationId::new(CryptoHash::from([5; 4]));
+        let other_app = ApplicationId::new(CryptoHash::from([6; 4]));
+        let set_governance = TokenOperation::SetGovernance { governance_app };
+        assert_ok(execute(&mut token, ADMIN, set_governance));
+
+        let mint = || TokenOperation::Mint {
+            to: ALICE,
+            amount: Amount::from_tokens(100),
+        };
+        assert!(matches!(
+            execute(&mut token, ALICE, mint()),
+            TokenResponse::Error(_)
+        ));
+
+        // Applications calling without a signer are refused rather than panicking,
+        // unless they are the governance app
+        token.runtime.set_authenticated_signer(None);
+        for (caller, minted) in [(other_app, false), (governance_app, true)] {
+            token.runtime.set_authenticated_caller_id(Some(caller));
+            let response = token
+                .execute_operation(mint())
+                .now_or_never()
+                .expect("Execution of token operation should not await anything");
+            assert_eq!(matches!(response, TokenResponse::Ok), minted);
+        }
+        assert_supply_invariant(&token, Amount::from_tokens(1_100));
+    }
+
+    #[test]
+    fn governance_funds_vesting_grants_from_its_own_account() {
+        let mut token = create_and_instantiate_token(parameters(None));
+        let governance_app = ApplicationId::new(CryptoHash::from([5; 4]));
+        let treasury = AccountOwner::from(governance_app);
+
+        let set_governance = TokenOperation::SetGovernance { governance_app };
+        assert_ok(execute(&mut token, ADMIN, set_governance));
+        let fund = TokenOperation::Transfer {
+            to: treasury,
+            amount: Amount::from_tokens(400),
+        };
+        assert_ok(execute(&mut token, ADMIN, fund));
+
+        // A proposal executed by governance carries no signer
+        token.runtime.set_authenticated_signer(None);
+        token
+            .runtime
+            .set_authenticated_caller_id(Some(governance_app));
+        let vest = TokenOperation::CreateVesting {
+            beneficiary: ALICE,
+            amount: Amount::from_tokens(300),
+            start: Timestamp::from(0),
+            cliff_micros: 0,
+            duration_micros: 1_000,
+        };
+        let response = token
+            .execute_operation(vest)
+            .now_or_never()
+            .expect("Execution of token operation should not await anything");
+        assert_ok(response);
+
+        let treasury_balance = token.state.balance(&treasury).now_or_never().unwrap();
+        assert_eq!(treasury_balance, Amount::from_tokens(100));
+        assert_supply_invariant(&token, Amount::from_tokens(1_000));
+    }
+
+    #[test]
+    fn statement_entries_are_appended_in_time_order() {
+        let mut token = create_and_instantiate_token(parameters(None));
+        for (time, tokens) in [(100, 1), (200, 2), (300, 3)] {
+            token.runtime.set_system_time(Timestamp::from(time));
+            let transfer = TokenOperation::Transfer {
+                to: ALICE,
+                amount: Amount::from_tokens(tokens),
+            };
+            assert_ok(execute(&mut token, ADMIN, transfer));
+        }
+
+        let state = &token.state;
+        assert_eq!(state.ledger_len_of(&ALICE).now_or_never().unwrap(), Ok(3));
+        let seq_at = |time| {
+            state
+                .ledger_seq_at(&ALICE, Timestamp::from(time))
+                .now_or_never()
+                .unwrap()
+        };
+        assert_eq!(seq_at(0), Ok(0));
+        assert_eq!(seq_at(200), Ok(1));
+        assert_eq!(seq_at(201), Ok(2));
+        assert_eq!(seq_at(301), Ok(3));
+
+        let entry = state
+            .ledger_entry(&ALICE, 2)
+            .now_or_never()
+            .unwrap()
+            .unwrap()
+            .expect("Third entry should exist");
+        assert_eq!(entry.amount, Amount::from_tokens(3));
+        assert_eq!(entry.counterparty, Some(ADMIN));
+    }
+
+    #[test]
+    fn voting_weight_uses_the_stake_held_before_a_given_time() {
+        let mut token = create_and_instantiate_token(parameters(None));
+        let stake = |tokens| TokenOperation::Stake {
+            amount: Amount::from_tokens(tokens),
+        };
+        let unstake = |tokens| TokenOperation::Unstake {
+            amount: Amount::from_tokens(tokens),
+        };
+        let steps = [
+            (100, stake(10)),
+            (100, stake(5)),
+            (200, stake(20)),
+            (300, unstake(35)),
+        ];
+        for (time, operation) in steps {
+            token.runtime.set_system_time(Timestamp::from(time));
+            assert_ok(execute(&mut token, ADMIN, operation));
+        }
+
+        let mut weight_at = |at: Option<u64>| {
+            let query = TokenOperation::VotingWeight {
+                owner: ADMIN,
+                at: at.map(Timestamp::from),
+            };
+            match execute(&mut token, BOB, query) {
+                TokenResponse::Balance(weight) => weight,
+                response => panic!("Unexpected response: {:?}", response),
+            }
+        };
+        assert_eq!(weight_at(Some(100)), Amount::ZERO);
+        assert_eq!(weight_at(Some(101)), Amount::from_tokens(15));
+        assert_eq!(weight_at(Some(200)), Amount::from_tokens(15));
+        assert_eq!(weight_at(Some(201)), Amount::from_tokens(35));
+        assert_eq!(weight_at(Some(301)), Amount::ZERO);
+        assert_eq!(weight_at(None), Amount::ZERO);
+    }
+
+    fn parameters(max_supply: Option<Amount>) -> TokenParameters {
+        TokenParameters {
+            initial_supply: Amount::from_tokens(1_000),
+            max_supply,
+            bonus_schedule: Default::default(),
+            unbonding_period_micros: None,
+        }
+    }
+
+    fn create_and_instantiate_token(parameters: TokenParameters) -> TokenContract {
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(parameters)
+            .with_application_id(ApplicationId::new(CryptoHash::from([7; 4])))
+            .with_chain_id(ChainId(CryptoHash::from([8; 4])))
+            .with_chain_ownership(ChainOwnership::single_super(ADMIN))
+            .with_authenticated_signer(Some(ADMIN))
+            .with_authenticated_caller_id(None)
+            .with_system_time(Timestamp::from(0));
+        let mut contract = TokenContract {
+            state: TokenState::load(runtime.root_view_storage_context())
+                .blocking_wait()
+                .expect("Failed to read from mock key value store"),
+            runtime,
+        };
+
+        contract
+            .instantiate(())
+            .now_or_never()
+            .expect("Initialization of token state should not await anything");
+        contract
+    }
+
+    fn execute(
+        contract: &mut TokenContract,
+        signer: AccountOwner,
+        operation: TokenOperation,
+    ) -> TokenResponse {
+        contract.runtime.set_authenticated_signer(Some(signer));
+        contract
+            .execute_operation(operation)
+            .now_or_never()
+            .expect("Execution of token operation should not await anything")
+    }
+
+    fn assert_ok(response: TokenResponse) {
+        assert!(
+            matches!(response, TokenResponse::Ok),
+            "Unexpected response: {:?}",
+            response
+        );
+    }
+
+    /// Supply is every balance (escrow and vault accounts included) plus tokens in flight
+    fn assert_supply_invariant(contract: &TokenContract, expected_supply: Amount) {
+        let state = &contract.state;
+        let mut balances = Amount::ZERO;
+        let owners = state
+            .accounts
+            .indices()
+            .now_or_never()
+            .expect("Reading balances should not await anything")
+            .expect("Failed to read balances");
+        for owner in owners {
+            let balance = state.balance(&owner).now_or_never().unwrap();
+            balances = balances.saturating_add(balance);
+        }
+
+        assert_eq!(*state.total_supply.get(), expected_supply);
+        assert_eq!(
+            *state.total_supply.get(),
+            balances.saturating_add(*state.total_in_flight.get())
+        );
+    }
+}
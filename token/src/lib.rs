@@ -1,6 +1,8 @@
-use async_graphql::{Request, Response, SimpleObject};
+use async_graphql::{
+    ComplexObject, Context, Enum, InputObject, Request, Response, SimpleObject,
+};
 use linera_sdk::{
-    linera_base_types::{AccountOwner, Amount, Timestamp},
+    linera_base_types::{Account, AccountOwner, Amount, ApplicationId, TimeDelta, Timestamp},
     abi::{ContractAbi, ServiceAbi},
     graphql::GraphQLMutationRoot,
 };
@@ -20,6 +22,14 @@ impl ServiceAbi for TokenAbi {
     type QueryResponse = Response;
 }
 
+/// Service ABI of the market app, so it can be queried without a crate dependency
+pub struct MarketQueryAbi;
+
+impl ServiceAbi for MarketQueryAbi {
+    type Query = Request;
+    type QueryResponse = Response;
+}
+
 /// Token operations
 #[derive(Clone, Debug, Deserialize, Serialize, GraphQLMutationRoot)]
 pub enum TokenOperation {
@@ -28,13 +38,113 @@ pub enum TokenOperation {
         to: AccountOwner,
         amount: Amount,
     },
+    /// Transfer tokens to an account on any chain
+    TransferToAccount {
+        to: Account,
+        amount: Amount,
+    },
+    /// Transfer tokens held by the calling application (e.g. escrow)
+    ApplicationTransfer {
+        to: AccountOwner,
+        amount: Amount,
+    },
+    /// Transfer tokens held by the calling application to an account on any chain
+    ApplicationTransferToAccount {
+        to: Account,
+        amount: Amount,
+    },
+    /// Allow `spender` (an account, or an application's account) to move up to
+    /// `amount` of the signer's tokens, or of the calling application's tokens.
+    /// An amount of zero revokes the allowance.
+    Approve {
+        spender: AccountOwner,
+        amount: Amount,
+        expires_at: Option<Timestamp>,
+    },
+    /// Move tokens out of `owner`'s account using an allowance. The spender is the
+    /// calling application if there is one, otherwise the signer.
+    TransferFrom {
+        owner: AccountOwner,
+        to: AccountOwner,
+        amount: Amount,
+    },
     /// Claim daily bonus
     ClaimBonus,
+    /// Configure anti-abuse guards for the daily bonus (admin only)
+    SetBonusGuards {
+        guards: BonusGuards,
+    },
+    /// Put an account on the bonus allowlist or denylist (admin only)
+    SetBonusAccess {
+        owner: AccountOwner,
+        access: BonusAccess,
+    },
     /// Mint tokens (admin only)
     Mint {
         to: AccountOwner,
         amount: Amount,
     },
+    /// Burn tokens from the signer's account
+    Burn {
+        amount: Amount,
+    },
+    /// Lock the signer's tokens in a vesting grant for `beneficiary` (admin only)
+    CreateVesting {
+        beneficiary: AccountOwner,
+        amount: Amount,
+        start: Timestamp,
+        cliff_micros: u64,
+        duration_micros: u64,
+    },
+    /// Claim everything that has vested for the signer
+    ClaimVested,
+    /// Stop a grant and return its unvested tokens to the grantor (admin only)
+    RevokeVesting {
+        vesting_id: u64,
+    },
+    /// Stake the signer's tokens to earn platform fees and voting weight
+    Stake {
+        amount: Amount,
+    },
+    /// Start unbonding staked tokens; they can be withdrawn after the unbonding period
+    Unstake {
+        amount: Amount,
+    },
+    /// Return all unbonded tokens to the signer's balance
+    WithdrawUnstaked,
+    /// Share tokens pro rata among stakers. Paid from the calling application's
+    /// account if there is one (e.g. market fees), otherwise from the signer's.
+    DistributeRewards {
+        amount: Amount,
+    },
+    /// Claim the signer's staking rewards
+    ClaimRewards,
+    /// Staked balance of `owner`, or what it was just before `at`,
+    /// returned as `TokenResponse::Balance`
+    VotingWeight {
+        owner: AccountOwner,
+        at: Option<Timestamp>,
+    },
+    /// Set the governance application allowed to run admin operations (admin only)
+    SetGovernance {
+        governance_app: ApplicationId,
+    },
+}
+
+/// Cross-chain token messages
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum TokenMessage {
+    /// Credit `target` on the receiving chain. Bounces back to `source` if rejected.
+    Credit {
+        source: AccountOwner,
+        target: AccountOwner,
+        amount: Amount,
+    },
+    /// Confirms a credit so the sending chain can clear it from in-flight amounts
+    Ack {
+        source: AccountOwner,
+        amount: Amount,
+    },
 }
 
 /// Token responses
@@ -49,20 +159,258 @@ pub enum TokenResponse {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TokenParameters {
     pub initial_supply: Amount,
+    /// Upper bound on the supply minted on a chain; uncapped if omitted
+    #[serde(default)]
+    pub max_supply: Option<Amount>,
+    /// Daily bonus amounts and streak rules
+    #[serde(default)]
+    pub bonus_schedule: BonusSchedule,
+    /// How long unstaked tokens stay locked; `STAKE_UNBONDING_PERIOD_MICROS` if omitted
+    #[serde(default)]
+    pub unbonding_period_micros: Option<u64>,
+}
+
+/// Tokens a spender may move on an owner's behalf
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct Allowance {
+    pub amount: Amount,
+    pub expires_at: Option<Timestamp>,
+}
+
+impl Allowance {
+    pub fn is_expired(&self, current_time: Timestamp) -> bool {
+        self.expires_at.is_some_and(|expires_at| current_time >= expires_at)
+    }
+}
+
+/// One line of an account statement
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct LedgerEntry {
+    pub kind: LedgerKind,
+    pub direction: EntryDirection,
+    pub amount: Amount,
+    /// The other side of the movement, if any
+    pub counterparty: Option<AccountOwner>,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
+pub enum LedgerKind {
+    Transfer,
+    CrossChainTransfer,
+    /// Tokens moved by a spender under an allowance
+    TransferFrom,
+    /// A bounced cross-chain transfer returned to the sender
+    Refund,
+    Mint,
+    Burn,
+    Bonus,
+    /// Tokens moved into an application's account (e.g. a market stake)
+    Escrow,
+    /// Tokens paid out of an application's account (e.g. winnings)
+    Payout,
+    /// Tokens locked in, released from or returned by a vesting grant
+    Vesting,
+    /// Tokens staked or withdrawn after unbonding
+    Stake,
+    /// Staking rewards distributed or claimed
+    Reward,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
+pub enum EntryDirection {
+    Credit,
+    Debit,
+}
+
+/// A vesting grant: nothing before the cliff, then linear release until `start + duration`
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+#[graphql(complex)]
+pub struct VestingSchedule {
+    pub id: u64,
+    pub beneficiary: AccountOwner,
+    pub grantor: AccountOwner,
+    pub total: Amount,
+    pub claimed: Amount,
+    pub start: Timestamp,
+    pub cliff_micros: u64,
+    pub duration_micros: u64,
+    /// Vesting stops at this time if the grant was revoked
+    pub revoked_at: Option<Timestamp>,
+}
+
+impl VestingSchedule {
+    /// Amount vested by `current_time`
+    pub fn vested_at(&self, current_time: Timestamp) -> Amount {
+        let time = match self.revoked_at {
+            Some(revoked_at) => current_time.min(revoked_at),
+            None => current_time,
+        };
+        let elapsed = time.delta_since(self.start).as_micros();
+
+        if time < self.start || elapsed < self.cliff_micros {
+            return Amount::ZERO;
+        }
+        if elapsed >= self.duration_micros {
+            return self.total;
+        }
+        Amount::from_attos(mul_div(
+            u128::from(self.total),
+            u128::from(elapsed),
+            u128::from(self.duration_micros),
+        ))
+    }
+
+    /// Vested but not yet claimed
+    pub fn claimable_at(&self, current_time: Timestamp) -> Amount {
+        self.vested_at(current_time).saturating_sub(self.claimed)
+    }
+}
+
+#[ComplexObject]
+impl VestingSchedule {
+    /// Amount vested so far; the service provides the current time as context data
+    async fn vested(&self, ctx: &Context<'_>) -> async_graphql::Result<Amount> {
+        Ok(self.vested_at(*ctx.data::<Timestamp>()?))
+    }
+
+    /// Amount that can be claimed now
+    async fn claimable(&self, ctx: &Context<'_>) -> async_graphql::Result<Amount> {
+        Ok(self.claimable_at(*ctx.data::<Timestamp>()?))
+    }
+}
+
+/// A staker's position
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
+pub struct StakePosition {
+    pub staked: Amount,
+    /// Rewards earned and not yet claimed
+    pub pending_rewards: Amount,
+    /// Reward index when `pending_rewards` was last brought up to date
+    #[graphql(skip)]
+    pub reward_index: u128,
+}
+
+impl StakePosition {
+    /// Credit rewards accrued since the last settlement at `reward_index`
+    pub fn settle(&mut self, reward_index: u128) {
+        let accrued = mul_div(
+            u128::from(self.staked),
+            reward_index.saturating_sub(self.reward_index),
+            REWARD_INDEX_SCALE,
+        );
+        self.pending_rewards = self.pending_rewards.saturating_add(Amount::from_attos(accrued));
+        self.reward_index = reward_index;
+    }
+}
+
+/// An account's staked balance from `timestamp` on
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StakeCheckpoint {
+    pub timestamp: Timestamp,
+    pub staked: Amount,
+}
+
+/// Unstaked tokens waiting out the unbonding period
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct Unbonding {
+    pub amount: Amount,
+    pub release_at: Timestamp,
+}
+
+/// A holder and their balance
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct Holder {
+    pub owner: AccountOwner,
+    pub balance: Amount,
+}
+
+/// Daily bonus state for an account as seen at query time
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct BonusStatus {
+    pub last_claim: Timestamp,
+    /// Last bonus paid
+    pub amount: Amount,
+    /// Current streak of consecutive daily claims
+    pub streak: u32,
+    pub can_claim: bool,
+    /// Bonus the next claim would pay
+    pub next_amount: Amount,
+    pub micros_until_next_claim: u64,
+}
+
+/// How daily bonuses grow with consecutive claims
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct BonusSchedule {
+    /// Bonus for the first day of a streak
+    pub base_amount: Amount,
+    /// Extra percent of the base added per consecutive day
+    pub streak_bonus_percent: u32,
+    /// Largest bonus a single claim can pay
+    pub max_amount: Amount,
+    /// Time after the cooldown during which a claim keeps the streak alive
+    pub streak_window_micros: u64,
+}
+
+impl Default for BonusSchedule {
+    fn default() -> Self {
+        Self {
+            base_amount: Amount::from_attos(DAILY_BONUS_AMOUNT),
+            streak_bonus_percent: 10,
+            max_amount: Amount::from_attos(DAILY_BONUS_AMOUNT * 2),
+            streak_window_micros: DAILY_BONUS_STREAK_WINDOW_MICROS,
+        }
+    }
+}
+
+impl BonusSchedule {
+    /// Bonus paid on day `streak` of a streak (1-based), capped at `max_amount`
+    pub fn amount_for(&self, streak: u32) -> Amount {
+        let extra_days = u128::from(streak.saturating_sub(1));
+        let percent = 100 + u128::from(self.streak_bonus_percent) * extra_days;
+        let amount = Amount::from_attos(u128::from(self.base_amount).saturating_mul(percent) / 100);
+        amount.min(self.max_amount)
+    }
+}
+
+/// Optional guards against farming the daily bonus with fresh accounts
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject, InputObject)]
+#[graphql(input_name = "BonusGuardsInput")]
+pub struct BonusGuards {
+    /// Most bonus tokens paid out per day, across all accounts
+    pub daily_emission_budget: Option<Amount>,
+    /// Market app in which an account must have bet before claiming
+    pub require_bet_on: Option<ApplicationId>,
+    /// Only allowlisted accounts may claim
+    pub allowlist_only: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Enum, PartialEq, Eq, Copy)]
+pub enum BonusAccess {
+    #[default]
+    Default,
+    /// Always eligible, skipping the bet requirement
+    Allowed,
+    /// Never eligible
+    Denied,
 }
 
 /// Daily bonus tracking
 #[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
 pub struct DailyBonus {
     pub last_claim: Timestamp,
+    /// Last bonus paid
     pub amount: Amount,
+    /// Consecutive daily claims, including the last one
+    pub streak: u32,
 }
 
 impl DailyBonus {
     pub fn new() -> Self {
         Self {
             last_claim: Timestamp::from(0),
-            amount: Amount::from_tokens(100), // 100 tokens
+            amount: Amount::ZERO,
+            streak: 0,
         }
     }
 
@@ -71,15 +419,52 @@ impl DailyBonus {
         delta >= DAILY_BONUS_COOLDOWN_MICROS
     }
 
-    pub fn claim(&mut self, current_time: Timestamp) -> Amount {
+    /// Streak length a claim at `current_time` would reach
+    pub fn next_streak(&self, current_time: Timestamp, schedule: &BonusSchedule) -> u32 {
+        let delta = current_time.delta_since(self.last_claim).as_micros();
+        let window_end = DAILY_BONUS_COOLDOWN_MICROS.saturating_add(schedule.streak_window_micros);
+        if self.streak > 0 && delta <= window_end {
+            self.streak.saturating_add(1)
+        } else {
+            1
+        }
+    }
+
+    pub fn claim(&mut self, current_time: Timestamp, schedule: &BonusSchedule) -> Amount {
         if self.can_claim(current_time) {
+            self.streak = self.next_streak(current_time, schedule);
             self.last_claim = current_time;
+            self.amount = schedule.amount_for(self.streak);
             self.amount
         } else {
             Amount::ZERO
         }
     }
 
+    pub fn status(&self, current_time: Timestamp, schedule: &BonusSchedule) -> BonusStatus {
+        let can_claim = self.can_claim(current_time);
+        // Until the bonus is claimable, show what a claim at the earliest moment would pay
+        let next_claim_time = if can_claim {
+            current_time
+        } else {
+            self.last_claim.saturating_add(TimeDelta::from_micros(DAILY_BONUS_COOLDOWN_MICROS))
+        };
+        let streak = if self.next_streak(current_time, schedule) == 1 {
+            0
+        } else {
+            self.streak
+        };
+
+        BonusStatus {
+            last_claim: self.last_claim,
+            amount: self.amount,
+            streak,
+            can_claim,
+            next_amount: schedule.amount_for(self.next_streak(next_claim_time, schedule)),
+            micros_until_next_claim: self.time_until_next_claim(current_time),
+        }
+    }
+
     pub fn time_until_next_claim(&self, current_time: Timestamp) -> u64 {
         let delta = current_time.delta_since(self.last_claim).as_micros();
         if delta >= DAILY_BONUS_COOLDOWN_MICROS {
@@ -89,3 +474,115 @@ impl DailyBonus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = DAILY_BONUS_COOLDOWN_MICROS;
+
+    fn at(micros: u64) -> Timestamp {
+        Timestamp::from(micros)
+    }
+
+    fn schedule() -> BonusSchedule {
+        BonusSchedule {
+            base_amount: Amount::from_tokens(100),
+            streak_bonus_percent: 10,
+            max_amount: Amount::from_tokens(120),
+            streak_window_micros: DAY,
+        }
+    }
+
+    #[test]
+    fn bonus_grows_with_the_streak_up_to_the_cap() {
+        let schedule = schedule();
+        assert_eq!(schedule.amount_for(0), Amount::from_tokens(100));
+        assert_eq!(schedule.amount_for(1), Amount::from_tokens(100));
+        assert_eq!(schedule.amount_for(2), Amount::from_tokens(110));
+        assert_eq!(schedule.amount_for(3), Amount::from_tokens(120));
+        assert_eq!(schedule.amount_for(30), Amount::from_tokens(120));
+    }
+
+    #[test]
+    fn consecutive_claims_extend_the_streak() {
+        let schedule = schedule();
+        let mut bonus = DailyBonus::new();
+
+        assert_eq!(bonus.claim(at(DAY), &schedule), Amount::from_tokens(100));
+        assert_eq!(bonus.streak, 1);
+
+        // Within the cooldown nothing is paid and the streak is untouched
+        assert_eq!(bonus.claim(at(DAY + DAY / 2), &schedule), Amount::ZERO);
+        assert_eq!(bonus.streak, 1);
+
+        assert_eq!(
+            bonus.claim(at(2 * DAY), &schedule),
+            Amount::from_tokens(110)
+        );
+        assert_eq!(bonus.streak, 2);
+
+        // Late, but still inside the streak window
+        assert_eq!(
+            bonus.claim(at(4 * DAY), &schedule),
+            Amount::from_tokens(120)
+        );
+        assert_eq!(bonus.streak, 3);
+    }
+
+    #[test]
+    fn missing_the_window_resets_the_streak() {
+        let schedule = schedule();
+        let mut bonus = DailyBonus::new();
+        bonus.claim(at(DAY), &schedule);
+        bonus.claim(at(2 * DAY), &schedule);
+        assert_eq!(bonus.streak, 2);
+
+        let status = bonus.status(at(4 * DAY + 1), &schedule);
+        assert!(status.can_claim);
+        assert_eq!(status.streak, 0);
+        assert_eq!(status.next_amount, Amount::from_tokens(100));
+
+        assert_eq!(
+            bonus.claim(at(4 * DAY + 1), &schedule),
+            Amount::from_tokens(100)
+        );
+        assert_eq!(bonus.streak, 1);
+    }
+
+    fn grant(cliff_micros: u64, duration_micros: u64) -> VestingSchedule {
+        VestingSchedule {
+            id: 0,
+            beneficiary: AccountOwner::Address20([2; 20]),
+            grantor: AccountOwner::Address20([1; 20]),
+            total: Amount::from_tokens(1_000),
+            claimed: Amount::ZERO,
+            start: at(1_000),
+            cliff_micros,
+            duration_micros,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn vesting_releases_linearly_after_the_cliff() {
+        let schedule = grant(250, 1_000);
+        assert_eq!(schedule.vested_at(at(0)), Amount::ZERO);
+        assert_eq!(schedule.vested_at(at(1_249)), Amount::ZERO);
+        assert_eq!(schedule.vested_at(at(1_250)), Amount::from_tokens(250));
+        assert_eq!(schedule.vested_at(at(1_500)), Amount::from_tokens(500));
+        assert_eq!(schedule.vested_at(at(2_000)), Amount::from_tokens(1_000));
+        assert_eq!(schedule.vested_at(at(9_000)), Amount::from_tokens(1_000));
+    }
+
+    #[test]
+    fn vesting_claims_and_revocation_cap_what_is_claimable() {
+        let mut schedule = grant(0, 1_000);
+        schedule.claimed = Amount::from_tokens(300);
+        assert_eq!(schedule.claimable_at(at(1_500)), Amount::from_tokens(200));
+
+        schedule.revoked_at = Some(at(1_400));
+        assert_eq!(schedule.vested_at(at(2_000)), Amount::from_tokens(400));
+        assert_eq!(schedule.claimable_at(at(2_000)), Amount::from_tokens(100));
+    }
+}
@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use async_graphql::{EmptySubscription, Object, Request, Response, Schema};
 use linera_sdk::{
+    linera_base_types::{Account, AccountOwner, Amount, ApplicationId, Timestamp},
     abi::WithServiceAbi,
     views::View,
     Service, ServiceRuntime,
@@ -14,6 +15,7 @@ use self::state::TokenState;
 
 pub struct TokenService {
     state: Arc<TokenState>,
+    runtime: Arc<ServiceRuntime<Self>>,
 }
 
 linera_sdk::service!(TokenService);
@@ -31,6 +33,7 @@ impl Service for TokenService {
             .expect("Failed to load state");
         TokenService {
             state: Arc::new(state),
+            runtime: Arc::new(runtime),
         }
     }
 
@@ -40,6 +43,8 @@ impl Service for TokenService {
             MutationRoot,
             EmptySubscription,
         )
+        .data(self.runtime.system_time())
+        .data(self.runtime.application_parameters())
         .finish();
 
         schema.execute(request).await
@@ -50,8 +55,100 @@ struct MutationRoot;
 
 #[Object]
 impl MutationRoot {
-    /// Get the current block height (placeholder for future features)
-    async fn placeholder(&self) -> bool {
-        true
+    async fn transfer(&self, to: AccountOwner, amount: Amount) -> Vec<u8> {
+        let op = token::TokenOperation::Transfer { to, amount };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn transfer_to_account(&self, to: Account, amount: Amount) -> Vec<u8> {
+        let op = token::TokenOperation::TransferToAccount { to, amount };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn claim_bonus(&self) -> Vec<u8> {
+        let op = token::TokenOperation::ClaimBonus;
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn mint(&self, to: AccountOwner, amount: Amount) -> Vec<u8> {
+        let op = token::TokenOperation::Mint { to, amount };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn burn(&self, amount: Amount) -> Vec<u8> {
+        let op = token::TokenOperation::Burn { amount };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn approve(
+        &self,
+        spender: AccountOwner,
+        amount: Amount,
+        expires_at: Option<Timestamp>,
+    ) -> Vec<u8> {
+        let op = token::TokenOperation::Approve {
+            spender,
+            amount,
+            expires_at,
+        };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn create_vesting(
+        &self,
+        beneficiary: AccountOwner,
+        amount: Amount,
+        start: Timestamp,
+        cliff_micros: u64,
+        duration_micros: u64,
+    ) -> Vec<u8> {
+        let op = token::TokenOperation::CreateVesting {
+            beneficiary,
+            amount,
+            start,
+            cliff_micros,
+            duration_micros,
+        };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn claim_vested(&self) -> Vec<u8> {
+        let op = token::TokenOperation::ClaimVested;
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn revoke_vesting(&self, vesting_id: u64) -> Vec<u8> {
+        let op = token::TokenOperation::RevokeVesting { vesting_id };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn stake(&self, amount: Amount) -> Vec<u8> {
+        let op = token::TokenOperation::Stake { amount };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn unstake(&self, amount: Amount) -> Vec<u8> {
+        let op = token::TokenOperation::Unstake { amount };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn withdraw_unstaked(&self) -> Vec<u8> {
+        let op = token::TokenOperation::WithdrawUnstaked;
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn distribute_rewards(&self, amount: Amount) -> Vec<u8> {
+        let op = token::TokenOperation::DistributeRewards { amount };
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn claim_rewards(&self) -> Vec<u8> {
+        let op = token::TokenOperation::ClaimRewards;
+        bcs::to_bytes(&op).unwrap()
+    }
+
+    async fn set_governance(&self, governance_app: ApplicationId) -> Vec<u8> {
+        let op = token::TokenOperation::SetGovernance { governance_app };
+        bcs::to_bytes(&op).unwrap()
     }
 }
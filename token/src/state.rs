@@ -1,15 +1,20 @@
-use async_graphql::SimpleObject;
+use async_graphql::{ComplexObject, Context, SimpleObject};
 use linera_sdk::{
-    linera_base_types::{AccountOwner, Amount},
+    linera_base_types::{AccountOwner, Amount, ApplicationId, Timestamp},
     views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
 };
 use serde::{Deserialize, Serialize};
 
-use token::DailyBonus;
+use token::{
+    Allowance, BonusAccess, BonusGuards, BonusStatus, DailyBonus, Holder, LedgerEntry,
+    StakeCheckpoint, StakePosition, TokenParameters, Unbonding, VestingSchedule,
+};
+use oracle_ai_abi::{mul_div, REWARD_INDEX_SCALE};
 
 /// Token application state
 #[derive(RootView, SimpleObject)]
 #[view(context = ViewStorageContext)]
+#[graphql(complex)]
 pub struct TokenState {
     /// Account balances
     pub accounts: MapView<AccountOwner, Amount>,
@@ -17,8 +22,179 @@ pub struct TokenState {
     /// Daily bonus tracking per user
     pub daily_bonuses: MapView<AccountOwner, DailyBonus>,
 
-    /// Total supply
+    /// Tokens on this chain: all balances plus amounts still in flight to other chains
     pub total_supply: RegisterView<Amount>,
+
+    /// Amounts sent to other chains and not yet acknowledged, per sender
+    pub in_flight: MapView<AccountOwner, Amount>,
+
+    /// Sum of all in-flight amounts
+    pub total_in_flight: RegisterView<Amount>,
+
+    /// Allowances per (owner, spender)
+    #[graphql(skip)]
+    pub allowances: MapView<(AccountOwner, AccountOwner), Allowance>,
+
+    /// Append-only statement entries per (account, sequence number)
+    #[graphql(skip)]
+    pub ledger: MapView<(AccountOwner, u64), LedgerEntry>,
+
+    /// Number of statement entries per account
+    #[graphql(skip)]
+    pub ledger_len: MapView<AccountOwner, u64>,
+
+    /// Daily bonus guards
+    pub bonus_guards: RegisterView<BonusGuards>,
+
+    /// Bonus allowlist and denylist
+    pub bonus_access: MapView<AccountOwner, BonusAccess>,
+
+    /// Day index (since epoch) of `bonus_emitted_today`
+    pub bonus_day: RegisterView<u64>,
+
+    /// Bonus tokens paid out on `bonus_day`
+    pub bonus_emitted_today: RegisterView<Amount>,
+
+    /// Vesting grants
+    pub vesting: MapView<u64, VestingSchedule>,
+
+    /// Vesting grant IDs per beneficiary
+    #[graphql(skip)]
+    pub vesting_by_beneficiary: MapView<AccountOwner, Vec<u64>>,
+
+    /// Next vesting grant ID
+    pub next_vesting_id: RegisterView<u64>,
+
+    /// Staked positions
+    #[graphql(skip)]
+    pub stakes: MapView<AccountOwner, StakePosition>,
+
+    /// Unstaked tokens still unbonding, per owner
+    #[graphql(skip)]
+    pub unbonding: MapView<AccountOwner, Vec<Unbonding>>,
+
+    /// Staked balance history per (account, sequence number), oldest first
+    #[graphql(skip)]
+    pub stake_history: MapView<(AccountOwner, u64), StakeCheckpoint>,
+
+    /// Number of stake checkpoints per account
+    #[graphql(skip)]
+    pub stake_history_len: MapView<AccountOwner, u64>,
+
+    /// Sum of all staked balances
+    pub total_staked: RegisterView<Amount>,
+
+    /// Rewards per staked token since launch, scaled by `REWARD_INDEX_SCALE`
+    #[graphql(skip)]
+    pub reward_index: RegisterView<u128>,
+
+    /// Rewards distributed to stakers since launch
+    pub total_rewards: RegisterView<Amount>,
+
+    /// Governance application allowed to run admin operations
+    pub governance_app: RegisterView<Option<ApplicationId>>,
+}
+
+#[ComplexObject]
+impl TokenState {
+    /// Balance of an account
+    #[graphql(name = "balance")]
+    async fn balance_of(&self, owner: AccountOwner) -> Amount {
+        self.balance(&owner).await
+    }
+
+    /// Daily bonus status; the service provides the current time and parameters as context data
+    async fn bonus_status(
+        &self,
+        ctx: &Context<'_>,
+        owner: AccountOwner,
+    ) -> async_graphql::Result<BonusStatus> {
+        let current_time = *ctx.data::<Timestamp>()?;
+        let schedule = &ctx.data::<TokenParameters>()?.bonus_schedule;
+        let bonus = self
+            .daily_bonuses
+            .get(&owner)
+            .await?
+            .unwrap_or_else(DailyBonus::new);
+        Ok(bonus.status(current_time, schedule))
+    }
+
+    /// Largest balances, biggest first
+    async fn top_holders(&self, limit: Option<u32>) -> async_graphql::Result<Vec<Holder>> {
+        let mut holders = Vec::new();
+        for owner in self.accounts.indices().await? {
+            let balance = self.balance(&owner).await;
+            if !balance.is_zero() {
+                holders.push(Holder { owner, balance });
+            }
+        }
+        holders.sort_by(|a, b| b.balance.cmp(&a.balance));
+        holders.truncate(limit.unwrap_or(10) as usize);
+        Ok(holders)
+    }
+
+    /// Allowance granted by `owner` to `spender`, if any
+    async fn allowance(&self, owner: AccountOwner, spender: AccountOwner) -> Option<Allowance> {
+        self.get_allowance(&owner, &spender).await
+    }
+
+    /// Ledger entries for `owner` between `from` and `to` (inclusive), oldest first.
+    /// Returns at most `limit` entries (default 100).
+    async fn statement(
+        &self,
+        owner: AccountOwner,
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+        offset: Option<u32>,
+        limit: Option<u32>,
+    ) -> async_graphql::Result<Vec<LedgerEntry>> {
+        let len = self.ledger_len_of(&owner).await?;
+        let first = match from {
+            Some(from) => self.ledger_seq_at(&owner, from).await?,
+            None => 0,
+        };
+        let start = first.saturating_add(u64::from(offset.unwrap_or(0)));
+        let end = start
+            .saturating_add(u64::from(limit.unwrap_or(100)))
+            .min(len);
+
+        let mut entries = Vec::new();
+        for seq in start..end {
+            let Some(entry) = self.ledger_entry(&owner, seq).await? else {
+                break;
+            };
+            if to.is_some_and(|to| entry.timestamp > to) {
+                break;
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// All vesting grants for a beneficiary
+    async fn vesting_schedules(
+        &self,
+        beneficiary: AccountOwner,
+    ) -> async_graphql::Result<Vec<VestingSchedule>> {
+        Ok(self.get_vesting_schedules(&beneficiary).await?)
+    }
+
+    /// Staked balance and unclaimed rewards of an account
+    #[graphql(name = "stake")]
+    async fn stake_of(&self, owner: AccountOwner) -> async_graphql::Result<StakePosition> {
+        Ok(self.stake_position(&owner).await?)
+    }
+
+    /// Unstaked tokens of an account still unbonding
+    #[graphql(name = "unbonding")]
+    async fn unbonding_of(&self, owner: AccountOwner) -> async_graphql::Result<Vec<Unbonding>> {
+        Ok(self.get_unbonding(&owner).await?)
+    }
+
+    /// Governance voting weight of an account (its staked balance)
+    async fn voting_weight(&self, owner: AccountOwner) -> async_graphql::Result<Amount> {
+        Ok(self.stake_position(&owner).await?.staked)
+    }
 }
 
 impl TokenState {
@@ -37,9 +213,6 @@ impl TokenState {
         let new_balance = current.saturating_add(amount);
         self.accounts.insert(owner, new_balance).map_err(|e| e.to_string())?;
 
-        let total = self.total_supply.get();
-        self.total_supply.set(total.saturating_add(amount));
-
         Ok(())
     }
 
@@ -57,9 +230,60 @@ impl TokenState {
         let new_balance = current.saturating_sub(amount);
         self.accounts.insert(owner, new_balance).map_err(|e| e.to_string())?;
 
-        let total = self.total_supply.get();
+        Ok(())
+    }
+
+    /// Create new tokens, respecting the optional supply cap
+    pub async fn mint(
+        &mut self,
+        owner: &AccountOwner,
+        amount: Amount,
+        max_supply: Option<Amount>,
+    ) -> Result<(), String> {
+        let total = *self.total_supply.get();
+        let new_total = total
+            .try_add(amount)
+            .map_err(|_| "Supply overflow".to_string())?;
+
+        if let Some(cap) = max_supply {
+            if new_total > cap {
+                return Err(format!("Mint would exceed the supply cap of {}", cap));
+            }
+        }
+
+        self.credit(owner, amount).await?;
+        self.total_supply.set(new_total);
+        Ok(())
+    }
+
+    /// Destroy tokens held by an account
+    pub async fn burn(&mut self, owner: &AccountOwner, amount: Amount) -> Result<(), String> {
+        self.debit(owner, amount).await?;
+
+        let total = *self.total_supply.get();
         self.total_supply.set(total.saturating_sub(amount));
+        Ok(())
+    }
 
+    /// Tokens arriving from another chain join this chain's supply
+    pub async fn receive(&mut self, owner: &AccountOwner, amount: Amount) -> Result<(), String> {
+        self.credit(owner, amount).await?;
+
+        let total = *self.total_supply.get();
+        self.total_supply.set(total.saturating_add(amount));
+        Ok(())
+    }
+
+    /// Tokens acknowledged by another chain leave this chain's supply
+    pub async fn complete_outgoing(
+        &mut self,
+        owner: &AccountOwner,
+        amount: Amount,
+    ) -> Result<(), String> {
+        self.settle_in_flight(owner, amount).await?;
+
+        let total = *self.total_supply.get();
+        self.total_supply.set(total.saturating_sub(amount));
         Ok(())
     }
 
@@ -74,4 +298,452 @@ impl TokenState {
         self.credit(to, amount).await?;
         Ok(())
     }
+
+    /// Amount a sender has in flight to other chains
+    pub async fn in_flight_of(&self, owner: &AccountOwner) -> Amount {
+        self.in_flight
+            .get(owner)
+            .await
+            .unwrap_or(None)
+            .unwrap_or(Amount::ZERO)
+    }
+
+    /// Track tokens leaving this chain
+    pub async fn add_in_flight(
+        &mut self,
+        owner: &AccountOwner,
+        amount: Amount,
+    ) -> Result<(), String> {
+        let current = self.in_flight_of(owner).await;
+        self.in_flight
+            .insert(owner, current.saturating_add(amount))
+            .map_err(|e| e.to_string())?;
+
+        let total = *self.total_in_flight.get();
+        self.total_in_flight.set(total.saturating_add(amount));
+        Ok(())
+    }
+
+    /// Stop tracking tokens once they were credited or bounced back
+    pub async fn settle_in_flight(
+        &mut self,
+        owner: &AccountOwner,
+        amount: Amount,
+    ) -> Result<(), String> {
+        let remaining = self.in_flight_of(owner).await.saturating_sub(amount);
+        if remaining.is_zero() {
+            self.in_flight.remove(owner).map_err(|e| e.to_string())?;
+        } else {
+            self.in_flight.insert(owner, remaining).map_err(|e| e.to_string())?;
+        }
+
+        let total = *self.total_in_flight.get();
+        self.total_in_flight.set(total.saturating_sub(amount));
+        Ok(())
+    }
+
+    /// Get the allowance `owner` granted to `spender`
+    pub async fn get_allowance(
+        &self,
+        owner: &AccountOwner,
+        spender: &AccountOwner,
+    ) -> Option<Allowance> {
+        self.allowances
+            .get(&(*owner, *spender))
+            .await
+            .unwrap_or(None)
+    }
+
+    /// Set (or, with a zero amount, revoke) an allowance
+    pub async fn approve(
+        &mut self,
+        owner: AccountOwner,
+        spender: AccountOwner,
+        allowance: Allowance,
+    ) -> Result<(), String> {
+        if allowance.amount.is_zero() {
+            return self
+                .allowances
+                .remove(&(owner, spender))
+                .map_err(|e| e.to_string());
+        }
+        self.allowances
+            .insert(&(owner, spender), allowance)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Spend from an allowance and move the tokens
+    pub async fn transfer_from(
+        &mut self,
+        spender: AccountOwner,
+        owner: &AccountOwner,
+        to: &AccountOwner,
+        amount: Amount,
+        current_time: Timestamp,
+    ) -> Result<(), String> {
+        let mut allowance = self
+            .get_allowance(owner, &spender)
+            .await
+            .ok_or_else(|| "No allowance".to_string())?;
+
+        if allowance.is_expired(current_time) {
+            return Err("Allowance expired".to_string());
+        }
+        if allowance.amount < amount {
+            return Err(format!(
+                "Insufficient allowance: {} < {}",
+                allowance.amount, amount
+            ));
+        }
+
+        self.transfer(owner, to, amount).await?;
+
+        allowance.amount = allowance.amount.saturating_sub(amount);
+        self.approve(*owner, spender, allowance).await
+    }
+
+    /// Number of ledger entries recorded for an account
+    pub async fn ledger_len_of(&self, owner: &AccountOwner) -> Result<u64, String> {
+        Ok(self
+            .ledger_len
+            .get(owner)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or(0))
+    }
+
+    /// Ledger entry `seq` of an account
+    pub async fn ledger_entry(
+        &self,
+        owner: &AccountOwner,
+        seq: u64,
+    ) -> Result<Option<LedgerEntry>, String> {
+        self.ledger
+            .get(&(*owner, seq))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// First sequence number of an account's entries recorded at or after `time`.
+    /// Entries are appended in time order, so this is a binary search.
+    pub async fn ledger_seq_at(
+        &self,
+        owner: &AccountOwner,
+        time: Timestamp,
+    ) -> Result<u64, String> {
+        let (mut low, mut high) = (0, self.ledger_len_of(owner).await?);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.ledger_entry(owner, mid).await? {
+                Some(entry) if entry.timestamp < time => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+        Ok(low)
+    }
+
+    /// Append an entry to an account's statement
+    pub async fn record(&mut self, owner: &AccountOwner, entry: LedgerEntry) -> Result<(), String> {
+        let seq = self.ledger_len_of(owner).await?;
+        self.ledger
+            .insert(&(*owner, seq), entry)
+            .map_err(|e| e.to_string())?;
+        self.ledger_len
+            .insert(owner, seq + 1)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Bonus allowlist/denylist status of an account
+    pub async fn bonus_access_of(&self, owner: &AccountOwner) -> BonusAccess {
+        self.bonus_access
+            .get(owner)
+            .await
+            .unwrap_or(None)
+            .unwrap_or_default()
+    }
+
+    /// Set an account's bonus access, dropping the entry when reset to default
+    pub async fn set_bonus_access(
+        &mut self,
+        owner: &AccountOwner,
+        access: BonusAccess,
+    ) -> Result<(), String> {
+        if access == BonusAccess::Default {
+            return self.bonus_access.remove(owner).map_err(|e| e.to_string());
+        }
+        self.bonus_access
+            .insert(owner, access)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Bonus tokens already paid out on `day`
+    pub fn bonus_emitted_on(&self, day: u64) -> Amount {
+        if *self.bonus_day.get() == day {
+            *self.bonus_emitted_today.get()
+        } else {
+            Amount::ZERO
+        }
+    }
+
+    /// Count a bonus towards the daily emission
+    pub fn add_bonus_emission(&mut self, day: u64, amount: Amount) {
+        let emitted = self.bonus_emitted_on(day).saturating_add(amount);
+        self.bonus_day.set(day);
+        self.bonus_emitted_today.set(emitted);
+    }
+
+    /// Store a new vesting grant
+    pub async fn create_vesting(&mut self, schedule: VestingSchedule) -> Result<u64, String> {
+        let id = *self.next_vesting_id.get();
+
+        let mut schedule_with_id = schedule;
+        schedule_with_id.id = id;
+
+        let mut ids = self.vesting_ids(&schedule_with_id.beneficiary).await?;
+        ids.push(id);
+        self.vesting_by_beneficiary
+            .insert(&schedule_with_id.beneficiary, ids)
+            .map_err(|e| e.to_string())?;
+
+        self.vesting
+            .insert(&id, schedule_with_id)
+            .map_err(|e| e.to_string())?;
+
+        self.next_vesting_id.set(id + 1);
+        Ok(id)
+    }
+
+    /// Get a vesting grant
+    pub async fn get_vesting(&self, id: u64) -> Result<VestingSchedule, String> {
+        self.vesting
+            .get(&id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Vesting grant {} not found", id))
+    }
+
+    /// Update a vesting grant
+    pub async fn update_vesting(&mut self, schedule: VestingSchedule) -> Result<(), String> {
+        let id = schedule.id;
+        self.vesting
+            .insert(&id, schedule)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Vesting grant IDs for a beneficiary
+    pub async fn vesting_ids(&self, beneficiary: &AccountOwner) -> Result<Vec<u64>, String> {
+        Ok(self
+            .vesting_by_beneficiary
+            .get(beneficiary)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default())
+    }
+
+    /// All vesting grants for a beneficiary
+    pub async fn get_vesting_schedules(
+        &self,
+        beneficiary: &AccountOwner,
+    ) -> Result<Vec<VestingSchedule>, String> {
+        let mut schedules = Vec::new();
+        for id in self.vesting_ids(beneficiary).await? {
+            schedules.push(self.get_vesting(id).await?);
+        }
+        Ok(schedules)
+    }
+
+    /// Staked position with rewards brought up to date
+    pub async fn stake_position(&self, owner: &AccountOwner) -> Result<StakePosition, String> {
+        let mut position = self
+            .stakes
+            .get(owner)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        position.settle(*self.reward_index.get());
+        Ok(position)
+    }
+
+    /// Add to an account's stake. The tokens must already be held by the app.
+    pub async fn stake(
+        &mut self,
+        owner: &AccountOwner,
+        amount: Amount,
+        current_time: Timestamp,
+    ) -> Result<(), String> {
+        let mut position = self.stake_position(owner).await?;
+        position.staked = position.staked.saturating_add(amount);
+        self.checkpoint_stake(owner, position.staked, current_time)
+            .await?;
+        self.stakes
+            .insert(owner, position)
+            .map_err(|e| e.to_string())?;
+
+        let total = *self.total_staked.get();
+        self.total_staked.set(total.saturating_add(amount));
+        Ok(())
+    }
+
+    /// Move staked tokens into unbonding until `release_at`
+    pub async fn unstake(
+        &mut self,
+        owner: &AccountOwner,
+        amount: Amount,
+        current_time: Timestamp,
+        release_at: Timestamp,
+    ) -> Result<(), String> {
+        let mut position = self.stake_position(owner).await?;
+        if position.staked < amount {
+            return Err("Insufficient staked balance".to_string());
+        }
+        position.staked = position.staked.saturating_sub(amount);
+        self.checkpoint_stake(owner, position.staked, current_time)
+            .await?;
+        self.stakes
+            .insert(owner, position)
+            .map_err(|e| e.to_string())?;
+
+        let total = *self.total_staked.get();
+        self.total_staked.set(total.saturating_sub(amount));
+
+        let mut entries = self.get_unbonding(owner).await?;
+        entries.push(Unbonding { amount, release_at });
+        self.unbonding
+            .insert(owner, entries)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Record an account's new staked balance. Several changes at the same time
+    /// keep a single checkpoint.
+    async fn checkpoint_stake(
+        &mut self,
+        owner: &AccountOwner,
+        staked: Amount,
+        timestamp: Timestamp,
+    ) -> Result<(), String> {
+        let len = self.stake_history_len_of(owner).await?;
+        if len > 0 {
+            let last = self.stake_checkpoint(owner, len - 1).await?;
+            if last.is_some_and(|last| last.timestamp == timestamp) {
+                let checkpoint = StakeCheckpoint { timestamp, staked };
+                return self
+                    .stake_history
+                    .insert(&(*owner, len - 1), checkpoint)
+                    .map_err(|e| e.to_string());
+            }
+        }
+
+        self.stake_history
+            .insert(&(*owner, len), StakeCheckpoint { timestamp, staked })
+            .map_err(|e| e.to_string())?;
+        self.stake_history_len
+            .insert(owner, len + 1)
+            .map_err(|e| e.to_string())
+    }
+
+    async fn stake_history_len_of(&self, owner: &AccountOwner) -> Result<u64, String> {
+        Ok(self
+            .stake_history_len
+            .get(owner)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or(0))
+    }
+
+    async fn stake_checkpoint(
+        &self,
+        owner: &AccountOwner,
+        seq: u64,
+    ) -> Result<Option<StakeCheckpoint>, String> {
+        self.stake_history
+            .get(&(*owner, seq))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Staked balance of an account just before `time`
+    pub async fn staked_before(
+        &self,
+        owner: &AccountOwner,
+        time: Timestamp,
+    ) -> Result<Amount, String> {
+        // Number of checkpoints strictly before `time`; they are recorded in time order
+        let (mut low, mut high) = (0, self.stake_history_len_of(owner).await?);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.stake_checkpoint(owner, mid).await? {
+                Some(checkpoint) if checkpoint.timestamp < time => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+        if low == 0 {
+            return Ok(Amount::ZERO);
+        }
+        Ok(self
+            .stake_checkpoint(owner, low - 1)
+            .await?
+            .map_or(Amount::ZERO, |checkpoint| checkpoint.staked))
+    }
+
+    /// Unbonding entries of an account
+    pub async fn get_unbonding(&self, owner: &AccountOwner) -> Result<Vec<Unbonding>, String> {
+        Ok(self
+            .unbonding
+            .get(owner)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default())
+    }
+
+    /// Remove and total the unbonding entries released by `current_time`
+    pub async fn take_unbonded(
+        &mut self,
+        owner: &AccountOwner,
+        current_time: Timestamp,
+    ) -> Result<Amount, String> {
+        let (released, pending): (Vec<_>, Vec<_>) = self
+            .get_unbonding(owner)
+            .await?
+            .into_iter()
+            .partition(|entry| entry.release_at <= current_time);
+
+        if pending.is_empty() {
+            self.unbonding.remove(owner).map_err(|e| e.to_string())?;
+        } else {
+            self.unbonding
+                .insert(owner, pending)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(released
+            .iter()
+            .fold(Amount::ZERO, |total, entry| total.saturating_add(entry.amount)))
+    }
+
+    /// Share `amount` among current stakers. The tokens must already be held by the app.
+    pub fn distribute_rewards(&mut self, amount: Amount) -> Result<(), String> {
+        let total_staked = *self.total_staked.get();
+        if total_staked.is_zero() {
+            return Err("No tokens are staked".to_string());
+        }
+
+        let increment = mul_div(u128::from(amount), REWARD_INDEX_SCALE, u128::from(total_staked));
+        let index = *self.reward_index.get();
+        self.reward_index.set(index.saturating_add(increment));
+
+        let total_rewards = *self.total_rewards.get();
+        self.total_rewards.set(total_rewards.saturating_add(amount));
+        Ok(())
+    }
+
+    /// Take an account's unclaimed rewards
+    pub async fn take_rewards(&mut self, owner: &AccountOwner) -> Result<Amount, String> {
+        let mut position = self.stake_position(owner).await?;
+        let rewards = position.pending_rewards;
+        position.pending_rewards = Amount::ZERO;
+        self.stakes
+            .insert(owner, position)
+            .map_err(|e| e.to_string())?;
+        Ok(rewards)
+    }
 }